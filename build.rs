@@ -3,13 +3,165 @@ use std::fs::File;
 use std::io::Write;
 use std::path::PathBuf;
 
+/// Memory sizes, peripherals and available packages of a chip variant.
+struct Variant {
+    name: &'static str,
+    flash: u32,
+    ram: u32,
+    peripheral_ram: u32,
+    peripherals: &'static [&'static str],
+    packages: &'static [&'static str],
+}
+
+/// Peripherals depending on the variant, each is emitted as cfg if the variant has it.
+/// Every variant has the EEPROM, the LPC1774 with 2K instead of 4K.
+const OPTIONAL_PERIPHERALS: &[&str] = &["eeprom", "ethernet", "lcd", "usb"];
+
+const PACKAGES: &[&str] = &["lqfp144", "lqfp208", "tfbga180", "tfbga208"];
+
+fn feature_enabled(name: &str) -> bool {
+    env::var_os(format!("CARGO_FEATURE_{}", name.to_uppercase())).is_some()
+}
+
+/// Sizes are in KiB.
+const VARIANTS: &[Variant] = &[
+    Variant {
+        name: "lpc1774",
+        flash: 128,
+        ram: 32,
+        peripheral_ram: 8,
+        peripherals: &["eeprom", "usb"],
+        packages: &["lqfp144", "lqfp208"],
+    },
+    Variant {
+        name: "lpc1776",
+        flash: 256,
+        ram: 64,
+        peripheral_ram: 16,
+        peripherals: &["eeprom", "ethernet", "usb"],
+        packages: &["lqfp208", "tfbga180"],
+    },
+    Variant {
+        name: "lpc1777",
+        flash: 512,
+        ram: 64,
+        peripheral_ram: 32,
+        peripherals: &["eeprom", "usb"],
+        packages: &["lqfp208"],
+    },
+    Variant {
+        name: "lpc1778",
+        flash: 512,
+        ram: 64,
+        peripheral_ram: 32,
+        peripherals: &["eeprom", "ethernet", "usb"],
+        packages: &["lqfp144", "lqfp208", "tfbga180", "tfbga208"],
+    },
+    Variant {
+        name: "lpc1785",
+        flash: 256,
+        ram: 64,
+        peripheral_ram: 16,
+        peripherals: &["eeprom", "lcd", "usb"],
+        packages: &["lqfp208"],
+    },
+    Variant {
+        name: "lpc1786",
+        flash: 256,
+        ram: 64,
+        peripheral_ram: 16,
+        peripherals: &["eeprom", "ethernet", "lcd", "usb"],
+        packages: &["lqfp208"],
+    },
+    Variant {
+        name: "lpc1787",
+        flash: 512,
+        ram: 64,
+        peripheral_ram: 32,
+        peripherals: &["eeprom", "lcd", "usb"],
+        packages: &["lqfp208"],
+    },
+    Variant {
+        name: "lpc1788",
+        flash: 512,
+        ram: 64,
+        peripheral_ram: 32,
+        peripherals: &["eeprom", "ethernet", "lcd", "usb"],
+        packages: &["lqfp144", "lqfp208", "tfbga180", "tfbga208"],
+    },
+];
+
+/// Size of one peripheral SRAM bank in KiB.
+const PERIPHERAL_RAM_BANK: u32 = 16;
+
+/// Places the `.ahbsramN` input sections in the peripheral SRAM bank `N`.
+///
+/// The sections are not initialized at startup, they are meant for DMA buffers.
+fn peripheral_ram_section(bank: u32) -> String {
+    format!(
+        "SECTIONS\n{{\n\t.ahbsram{0} (NOLOAD) : ALIGN(4)\n\t{{\n\t\t*(.ahbsram{0} .ahbsram{0}.*);\n\t\t. = ALIGN(4);\n\t}} > AHBSRAM{0}\n}} INSERT AFTER .bss;\n",
+        bank
+    )
+}
+
 fn main() {
+    let selected: Vec<&Variant> = VARIANTS
+        .iter()
+        .filter(|v| feature_enabled(v.name))
+        .collect();
+    // Without a variant feature the smallest variant is assumed, which runs on every chip
+    let variant = match selected.as_slice() {
+        [] => &VARIANTS[0],
+        [variant] => variant,
+        _ => panic!("Only one chip variant feature may be selected"),
+    };
+    // Not every variant is sold in every package, see the ordering options of the data sheet
+    let package = PACKAGES.iter().find(|p| feature_enabled(p));
+    if let ([variant], Some(package)) = (selected.as_slice(), package) {
+        if !variant.packages.contains(package) {
+            panic!(
+                "The {} is not available in the {} package, it comes in {}",
+                variant.name.to_uppercase(),
+                package.to_uppercase(),
+                variant.packages.join(", ").to_uppercase()
+            );
+        }
+    }
+
+    let mut memory = format!(
+        "MEMORY\n{{\n\tFLASH : ORIGIN = 0x00000000, LENGTH = {}K\n\tRAM : ORIGIN = 0x10000000, LENGTH = {}K\n",
+        variant.flash, variant.ram
+    );
+    let bank0 = variant.peripheral_ram.min(PERIPHERAL_RAM_BANK);
+    memory += &format!("\tAHBSRAM0 : ORIGIN = 0x20000000, LENGTH = {}K\n", bank0);
+    if variant.peripheral_ram > PERIPHERAL_RAM_BANK {
+        memory += &format!(
+            "\tAHBSRAM1 : ORIGIN = 0x20004000, LENGTH = {}K\n",
+            variant.peripheral_ram - PERIPHERAL_RAM_BANK
+        );
+    }
+    memory += "}\n";
+
+    memory += &peripheral_ram_section(0);
+    println!("cargo:rustc-check-cfg=cfg(ahbsram1)");
+    if variant.peripheral_ram > PERIPHERAL_RAM_BANK {
+        memory += &peripheral_ram_section(1);
+        println!("cargo:rustc-cfg=ahbsram1");
+    }
+
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
     File::create(out.join("memory.x"))
         .unwrap()
-        .write_all(include_bytes!("memory.x"))
+        .write_all(memory.as_bytes())
         .unwrap();
     println!("cargo:rustc-link-search={}", out.display());
 
-    println!("cargo:rerun-if-changed=memory.x");
+    for peripheral in OPTIONAL_PERIPHERALS {
+        println!("cargo:rustc-check-cfg=cfg({})", peripheral);
+    }
+    for peripheral in variant.peripherals {
+        println!("cargo:rustc-cfg={}", peripheral);
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
 }
@@ -0,0 +1,38 @@
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use cortex_m_semihosting::hprintln;
+use panic_halt as _;
+
+use lpc178x_7x_hal::*;
+
+#[entry]
+#[allow(unused_must_use)]
+fn main() -> ! {
+    let hal = Hal::new();
+    let clock = hal.clock.enable(96_000_000, 12_000_000);
+    let pins = hal.gpio0.split();
+    let tx = pins.p0_15.into_function::<gpio::function::U1Txd>();
+    let rx = pins.p0_16.into_function::<gpio::function::U1Rxd>();
+    let mut uart = hal.uart1.enable(clock.get_uart_config(115200), rx, tx);
+    uart.set_loopback(true);
+    if let Ok(()) = nb::block!(uart.try_write(b'!')) {
+        nb::block!(uart.try_flush());
+        match nb::block!(uart.try_read()) {
+            Ok(byte) => {
+                if byte == b'!' {
+                    hprintln!("[OK]");
+                } else {
+                    hprintln!("[Fail]: Wrote '!' but received '{}'", byte);
+                }
+            }
+            Err(_) => {
+                hprintln!("[Fail]: Reading failed");
+            }
+        }
+    } else {
+        hprintln!("[Fail]: Writing failed");
+    }
+    loop {}
+}
@@ -13,8 +13,8 @@ fn main() -> ! {
     let hal = Hal::new();
     let clock = hal.clock.enable(96_000_000, 12_000_000);
     let pins = hal.gpio0.split();
-    let tx = pins.p0_2;
-    let rx = pins.p0_3;
+    let tx = pins.p0_2.into_function::<gpio::function::U0Txd>();
+    let rx = pins.p0_3.into_function::<gpio::function::U0Rxd>();
     let mut uart = hal.uart0.enable(clock.get_uart_config(115200), rx, tx);
     if let Ok(()) = nb::block!(uart.try_write(b'!')) {
         nb::block!(uart.try_flush());
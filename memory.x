@@ -1,7 +0,0 @@
-MEMORY
-{
-	FLASH : ORIGIN = 0x00000000, LENGTH = 256K
-	RAM : ORIGIN = 0x20000000, LENGTH = 64K
-}
-
-
@@ -0,0 +1,47 @@
+//! Placement of static buffers in the peripheral SRAM.
+//!
+//! The peripheral SRAM banks sit on the AHB matrix next to the Ethernet, USB and GPDMA
+//! controllers, so DMA buffers placed there don't compete with the CPU for the main SRAM.
+//! `build.rs` emits the `.ahbsram0` and `.ahbsram1` linker sections, bank 1 only exists on
+//! variants with 32K peripheral SRAM.
+//!
+//! The sections are not initialized at startup, so buffers are declared as `MaybeUninit`.
+
+/// Places `static mut` buffers in peripheral SRAM bank 0.
+///
+/// Each buffer of type `T` is declared as `MaybeUninit<T>` and must be initialized before use.
+///
+/// # Example
+/// ```
+/// lpc178x_7x_hal::ahbsram0! {
+///     static mut RX_BUFFER: [u8; 1536];
+///     static mut TX_BUFFER: [u8; 1536];
+/// }
+///
+/// let rx = unsafe { RX_BUFFER.write([0; 1536]) };
+/// ```
+#[macro_export]
+macro_rules! ahbsram0 {
+    ($($(#[$attr: meta])* $vis: vis static mut $name: ident: $type: ty;)+) => {
+        $(
+            $(#[$attr])*
+            #[link_section = ".ahbsram0"]
+            $vis static mut $name: ::core::mem::MaybeUninit<$type> = ::core::mem::MaybeUninit::uninit();
+        )+
+    };
+}
+
+/// Places `static mut` buffers in peripheral SRAM bank 1.
+///
+/// Only available on variants with 32K peripheral SRAM, see `ahbsram0!`.
+#[cfg(ahbsram1)]
+#[macro_export]
+macro_rules! ahbsram1 {
+    ($($(#[$attr: meta])* $vis: vis static mut $name: ident: $type: ty;)+) => {
+        $(
+            $(#[$attr])*
+            #[link_section = ".ahbsram1"]
+            $vis static mut $name: ::core::mem::MaybeUninit<$type> = ::core::mem::MaybeUninit::uninit();
+        )+
+    };
+}
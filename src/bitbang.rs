@@ -0,0 +1,8 @@
+//! Software implementations of serial buses on GPIO pins.
+//!
+//! The buses are clocked by a periodic timer, one timer period is half a clock period of the
+//! bus. They work with any pins implementing the embedded-hal digital traits, e.g. for extra
+//! buses on pins without a hardware SSP or I2C function.
+
+pub mod i2c;
+pub mod spi;
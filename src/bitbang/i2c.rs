@@ -0,0 +1,246 @@
+//! Bit-banged I2C master.
+//!
+//! SCL and SDA need open-drain pins with pull-up resistors, e.g. pins converted with
+//! `into_open_drain_output`. Clock stretching by slaves is supported.
+
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::timer::{CountDown, Periodic};
+
+/// Number of half clock periods a slave may stretch the clock before `Error::Timeout`.
+const MAX_CLOCK_STRETCH: u32 = 1000;
+
+/// Errors of the bit-banged I2C master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// Driving or reading a pin failed.
+    Pin(E),
+    /// Starting or waiting for the timer failed.
+    Timer,
+    /// The slave did not acknowledge its address or a written byte.
+    NoAck,
+    /// A slave held SCL low for too long.
+    Timeout,
+    /// The address is not a 7-bit address.
+    InvalidAddress,
+}
+
+/// A bit-banged I2C master.
+pub struct I2c<Scl, Sda, T> {
+    scl: Scl,
+    sda: Sda,
+    timer: T,
+}
+
+impl<Scl, Sda, T, E> I2c<Scl, Sda, T>
+where
+    Scl: OutputPin<Error = E> + InputPin<Error = E>,
+    Sda: OutputPin<Error = E> + InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+    /// Creates the I2C master and releases both lines.
+    ///
+    /// # Arguments
+    ///
+    /// * scl - The open-drain clock pin.
+    /// * sda - The open-drain data pin.
+    /// * timer - A periodic timer, started with `half_period`.
+    /// * half_period - Half a clock period, e.g. 5 µs for 100 kHz.
+    ///
+    /// # Example
+    /// ```
+    /// let pins = hal.gpio0.split();
+    /// let timer = hal.timer1.enable(&clock).into_periodic();
+    /// let mut i2c = I2c::new(
+    ///     pins.p0_10.into_open_drain_output(),
+    ///     pins.p0_11.into_open_drain_output(),
+    ///     timer,
+    ///     5.microseconds(),
+    /// ).unwrap();
+    /// i2c.try_write(0x50, &[0x00, 0x42]).unwrap();
+    /// ```
+    pub fn new<P>(scl: Scl, sda: Sda, mut timer: T, half_period: P) -> Result<Self, Error<E>>
+    where
+        P: Into<T::Time>,
+    {
+        timer.try_start(half_period).map_err(|_| Error::Timer)?;
+        let mut i2c = I2c { scl, sda, timer };
+        i2c.sda.try_set_high().map_err(Error::Pin)?;
+        i2c.scl.try_set_high().map_err(Error::Pin)?;
+        Ok(i2c)
+    }
+
+    /// Releases the pins and the timer.
+    pub fn free(self) -> (Scl, Sda, T) {
+        (self.scl, self.sda, self.timer)
+    }
+
+    fn wait(&mut self) -> Result<(), Error<E>> {
+        nb::block!(self.timer.try_wait()).map_err(|_| Error::Timer)
+    }
+
+    fn set_sda(&mut self, high: bool) -> Result<(), Error<E>> {
+        if high {
+            self.sda.try_set_high().map_err(Error::Pin)
+        } else {
+            self.sda.try_set_low().map_err(Error::Pin)
+        }
+    }
+
+    fn scl_low(&mut self) -> Result<(), Error<E>> {
+        self.scl.try_set_low().map_err(Error::Pin)
+    }
+
+    /// Releases SCL and waits until slaves stop stretching the clock.
+    fn scl_high(&mut self) -> Result<(), Error<E>> {
+        self.scl.try_set_high().map_err(Error::Pin)?;
+        for _ in 0..MAX_CLOCK_STRETCH {
+            if self.scl.try_is_high().map_err(Error::Pin)? {
+                return Ok(());
+            }
+            self.wait()?;
+        }
+        Err(Error::Timeout)
+    }
+
+    /// Generates a start condition, or a repeated start condition while SCL is low.
+    fn start(&mut self) -> Result<(), Error<E>> {
+        self.set_sda(true)?;
+        self.wait()?;
+        self.scl_high()?;
+        self.wait()?;
+        self.set_sda(false)?;
+        self.wait()?;
+        self.scl_low()
+    }
+
+    fn stop(&mut self) -> Result<(), Error<E>> {
+        self.set_sda(false)?;
+        self.wait()?;
+        self.scl_high()?;
+        self.wait()?;
+        self.set_sda(true)?;
+        self.wait()
+    }
+
+    fn write_bit(&mut self, bit: bool) -> Result<(), Error<E>> {
+        self.set_sda(bit)?;
+        self.wait()?;
+        self.scl_high()?;
+        self.wait()?;
+        self.scl_low()
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Error<E>> {
+        self.set_sda(true)?;
+        self.wait()?;
+        self.scl_high()?;
+        self.wait()?;
+        let bit = self.sda.try_is_high().map_err(Error::Pin)?;
+        self.scl_low()?;
+        Ok(bit)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error<E>> {
+        for i in (0..8).rev() {
+            self.write_bit(byte & (1 << i) != 0)?;
+        }
+        // The slave acknowledges by pulling SDA low
+        if self.read_bit()? {
+            Err(Error::NoAck)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error<E>> {
+        let mut byte = 0;
+        for _ in 0..8 {
+            byte = (byte << 1) | self.read_bit()? as u8;
+        }
+        self.write_bit(!ack)?;
+        Ok(byte)
+    }
+
+    fn write_bytes(&mut self, address: u8, bytes: &[u8]) -> Result<(), Error<E>> {
+        self.write_byte(address << 1)?;
+        for &byte in bytes {
+            self.write_byte(byte)?;
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.write_byte((address << 1) | 1)?;
+        let last = buffer.len().saturating_sub(1);
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            // The last byte is not acknowledged, which ends the transfer
+            *byte = self.read_byte(i != last)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `transfer` with the slave at `address` between a start and a stop condition.
+    ///
+    /// The stop condition is also generated if the transfer fails, to release the bus. Addresses
+    /// above 0x7F are rejected before the bus is touched.
+    fn transaction<F>(&mut self, address: u8, transfer: F) -> Result<(), Error<E>>
+    where
+        F: FnOnce(&mut Self) -> Result<(), Error<E>>,
+    {
+        if address > 0x7F {
+            return Err(Error::InvalidAddress);
+        }
+        let result = self.start().and_then(|_| transfer(self));
+        let stop = self.stop();
+        result.and(stop)
+    }
+}
+
+impl<Scl, Sda, T, E> Write for I2c<Scl, Sda, T>
+where
+    Scl: OutputPin<Error = E> + InputPin<Error = E>,
+    Sda: OutputPin<Error = E> + InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+    type Error = Error<E>;
+
+    fn try_write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.transaction(address, |i2c| i2c.write_bytes(address, bytes))
+    }
+}
+
+impl<Scl, Sda, T, E> Read for I2c<Scl, Sda, T>
+where
+    Scl: OutputPin<Error = E> + InputPin<Error = E>,
+    Sda: OutputPin<Error = E> + InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+    type Error = Error<E>;
+
+    fn try_read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.transaction(address, |i2c| i2c.read_bytes(address, buffer))
+    }
+}
+
+impl<Scl, Sda, T, E> WriteRead for I2c<Scl, Sda, T>
+where
+    Scl: OutputPin<Error = E> + InputPin<Error = E>,
+    Sda: OutputPin<Error = E> + InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+    type Error = Error<E>;
+
+    fn try_write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.transaction(address, |i2c| {
+            i2c.write_bytes(address, bytes)?;
+            i2c.start()?;
+            i2c.read_bytes(address, buffer)
+        })
+    }
+}
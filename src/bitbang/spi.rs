@@ -0,0 +1,201 @@
+//! Bit-banged SPI master.
+
+use embedded_hal::blocking::spi::{transfer, write};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::spi::{FullDuplex, Mode, Phase, Polarity};
+use embedded_hal::timer::{CountDown, Periodic};
+
+/// Errors of the bit-banged SPI master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// Driving or reading a pin failed.
+    Pin(E),
+    /// Starting or waiting for the timer failed.
+    Timer,
+    /// `try_read` was called without a preceding `try_send`.
+    NoData,
+}
+
+/// Order in which the bits of a word are transferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// A bit-banged SPI master, supporting all four SPI modes.
+pub struct Spi<Sck, Mosi, Miso, T> {
+    sck: Sck,
+    mosi: Mosi,
+    miso: Miso,
+    timer: T,
+    mode: Mode,
+    bit_order: BitOrder,
+    received: Option<u8>,
+}
+
+impl<Sck, Mosi, Miso, T, E> Spi<Sck, Mosi, Miso, T>
+where
+    Sck: OutputPin<Error = E>,
+    Mosi: OutputPin<Error = E>,
+    Miso: InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+    /// Creates the SPI master and puts the clock line into its idle state.
+    ///
+    /// # Arguments
+    ///
+    /// * sck - The clock output.
+    /// * mosi - The data output.
+    /// * miso - The data input.
+    /// * mode - Clock polarity and phase.
+    /// * timer - A periodic timer, started with `half_period`.
+    /// * half_period - Half a clock period, e.g. 5 µs for 100 kHz.
+    ///
+    /// # Example
+    /// ```
+    /// let pins = hal.gpio1.split();
+    /// let timer = hal.timer1.enable(&clock).into_periodic();
+    /// let mut spi = Spi::new(
+    ///     pins.p1_0.into_output(),
+    ///     pins.p1_1.into_output(),
+    ///     pins.p1_4.into_input(),
+    ///     MODE_0,
+    ///     timer,
+    ///     5.microseconds(),
+    /// ).unwrap();
+    /// ```
+    pub fn new<P>(
+        sck: Sck,
+        mosi: Mosi,
+        miso: Miso,
+        mode: Mode,
+        mut timer: T,
+        half_period: P,
+    ) -> Result<Self, Error<E>>
+    where
+        P: Into<T::Time>,
+    {
+        timer.try_start(half_period).map_err(|_| Error::Timer)?;
+        let mut spi = Spi {
+            sck,
+            mosi,
+            miso,
+            timer,
+            mode,
+            bit_order: BitOrder::MsbFirst,
+            received: None,
+        };
+        spi.set_clock(false)?;
+        Ok(spi)
+    }
+
+    /// Selects the order in which the bits of a word are transferred, `MsbFirst` by default.
+    pub fn set_bit_order(&mut self, bit_order: BitOrder) {
+        self.bit_order = bit_order;
+    }
+
+    /// Releases the pins and the timer.
+    pub fn free(self) -> (Sck, Mosi, Miso, T) {
+        (self.sck, self.mosi, self.miso, self.timer)
+    }
+
+    fn wait(&mut self) -> Result<(), Error<E>> {
+        nb::block!(self.timer.try_wait()).map_err(|_| Error::Timer)
+    }
+
+    fn set_clock(&mut self, active: bool) -> Result<(), Error<E>> {
+        let high = match self.mode.polarity {
+            Polarity::IdleLow => active,
+            Polarity::IdleHigh => !active,
+        };
+        if high {
+            self.sck.try_set_high().map_err(Error::Pin)
+        } else {
+            self.sck.try_set_low().map_err(Error::Pin)
+        }
+    }
+
+    fn set_data(&mut self, bit: bool) -> Result<(), Error<E>> {
+        if bit {
+            self.mosi.try_set_high().map_err(Error::Pin)
+        } else {
+            self.mosi.try_set_low().map_err(Error::Pin)
+        }
+    }
+
+    fn transfer_bit(&mut self, bit: bool) -> Result<bool, Error<E>> {
+        match self.mode.phase {
+            Phase::CaptureOnFirstTransition => {
+                self.set_data(bit)?;
+                self.wait()?;
+                self.set_clock(true)?;
+                let received = self.miso.try_is_high().map_err(Error::Pin)?;
+                self.wait()?;
+                self.set_clock(false)?;
+                Ok(received)
+            }
+            Phase::CaptureOnSecondTransition => {
+                self.set_clock(true)?;
+                self.set_data(bit)?;
+                self.wait()?;
+                self.set_clock(false)?;
+                let received = self.miso.try_is_high().map_err(Error::Pin)?;
+                self.wait()?;
+                Ok(received)
+            }
+        }
+    }
+
+    fn transfer_word(&mut self, word: u8) -> Result<u8, Error<E>> {
+        let mut received = 0;
+        for i in 0..8 {
+            let bit = match self.bit_order {
+                BitOrder::MsbFirst => 7 - i,
+                BitOrder::LsbFirst => i,
+            };
+            if self.transfer_bit(word & (1 << bit) != 0)? {
+                received |= 1 << bit;
+            }
+        }
+        Ok(received)
+    }
+}
+
+impl<Sck, Mosi, Miso, T, E> FullDuplex<u8> for Spi<Sck, Mosi, Miso, T>
+where
+    Sck: OutputPin<Error = E>,
+    Mosi: OutputPin<Error = E>,
+    Miso: InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+    type Error = Error<E>;
+
+    fn try_read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.received.take().ok_or(nb::Error::Other(Error::NoData))
+    }
+
+    /// Transfers the word, blocking until all bits have been clocked out.
+    fn try_send(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.received = Some(self.transfer_word(word)?);
+        Ok(())
+    }
+}
+
+impl<Sck, Mosi, Miso, T, E> transfer::Default<u8> for Spi<Sck, Mosi, Miso, T>
+where
+    Sck: OutputPin<Error = E>,
+    Mosi: OutputPin<Error = E>,
+    Miso: InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+}
+
+impl<Sck, Mosi, Miso, T, E> write::Default<u8> for Spi<Sck, Mosi, Miso, T>
+where
+    Sck: OutputPin<Error = E>,
+    Mosi: OutputPin<Error = E>,
+    Miso: InputPin<Error = E>,
+    T: CountDown + Periodic,
+{
+}
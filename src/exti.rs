@@ -0,0 +1,102 @@
+use crate::gpio::{self, function};
+use crate::pac;
+use crate::typestates::AltFunction;
+
+/// Whether an external interrupt is triggered by the level or by an edge of its pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Level,
+    Edge,
+}
+
+/// The active level or edge of an external interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Polarity {
+    /// Low level or falling edge.
+    LowFalling,
+    /// High level or rising edge.
+    HighRising,
+}
+
+macro_rules! exti {
+    ($(($type: ident, $pin: ident, $io: ident, $function: ident, $func: ident, $bit: expr)),* $(,)?) => {
+        $(
+            gpio::pin_functions!(($pin, $io, $function, $func));
+
+            #[doc="A HAL wrapper for the external interrupt input $function"]
+            pub struct $type {
+                pin: gpio::$pin<AltFunction<function::$function>>,
+            }
+
+            impl $type {
+                /// Sets up the external interrupt and clears its pending flag.
+                ///
+                /// The external interrupts are the only interrupts that can wake the MCU from
+                /// deep sleep. They still need to be unmasked in the NVIC.
+                ///
+                /// # Arguments
+                ///
+                /// * pin - The pin connected to the external interrupt function.
+                /// * mode - Whether the interrupt is level or edge sensitive.
+                /// * polarity - The active level or edge.
+                ///
+                /// # Example
+                /// ```
+                /// let pins = hal.gpio2.split();
+                /// let eint0 = exti::Eint0::new(
+                ///     pins.p2_10.into_function::<gpio::function::Eint0>(),
+                ///     exti::Mode::Edge,
+                ///     exti::Polarity::LowFalling,
+                /// );
+                /// ```
+                pub fn new(pin: gpio::$pin<AltFunction<function::$function>>, mode: Mode, polarity: Polarity) -> Self {
+                    let mut exti = $type { pin };
+                    exti.set_trigger(mode, polarity);
+                    exti
+                }
+
+                /// Changes the trigger of the external interrupt and clears its pending flag.
+                pub fn set_trigger(&mut self, mode: Mode, polarity: Polarity) {
+                    let syscon = unsafe { &(*pac::SYSCON::ptr()) };
+                    unsafe {
+                        syscon.extmode.modify(|r, w| match mode {
+                            Mode::Level => w.bits(r.bits() & !(1 << $bit)),
+                            Mode::Edge => w.bits(r.bits() | (1 << $bit)),
+                        });
+                        syscon.extpolar.modify(|r, w| match polarity {
+                            Polarity::LowFalling => w.bits(r.bits() & !(1 << $bit)),
+                            Polarity::HighRising => w.bits(r.bits() | (1 << $bit)),
+                        });
+                    }
+                    // Changing mode or polarity may set the interrupt flag
+                    self.clear_pending();
+                }
+
+                /// Returns whether the external interrupt is pending.
+                pub fn is_pending(&self) -> bool {
+                    unsafe { ((*pac::SYSCON::ptr()).extint.read().bits() & (1 << $bit)) != 0 }
+                }
+
+                /// Clears the pending flag of the external interrupt.
+                ///
+                /// In level sensitive mode the flag can only be cleared once the pin is no longer
+                /// at its active level.
+                pub fn clear_pending(&mut self) {
+                    unsafe { (*pac::SYSCON::ptr()).extint.write(|w| w.bits(1 << $bit)); }
+                }
+
+                /// Releases the pin of the external interrupt.
+                pub fn release(self) -> gpio::$pin<AltFunction<function::$function>> {
+                    self.pin
+                }
+            }
+        )*
+    }
+}
+
+exti!(
+    (Eint0, P2_10, p2_10, Eint0, eint0, 0),
+    (Eint1, P2_11, p2_11, Eint1, eint1, 1),
+    (Eint2, P2_12, p2_12, Eint2, eint2, 2),
+    (Eint3, P2_13, p2_13, Eint3, eint3, 3),
+);
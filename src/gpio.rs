@@ -1,11 +1,287 @@
 use crate::pac;
 use crate::typestates;
 use crate::typestates::gpio::direction;
+use crate::typestates::gpio::function::Function;
 use core::marker::PhantomData;
-use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::digital::{toggleable, InputPin, OutputPin, StatefulOutputPin};
+
+pub use crate::typestates::gpio::function;
+
+pub mod debounce;
+
+/// Implemented by pins that can be connected to the peripheral function `F`.
+///
+/// Peripheral drivers implement this for the pins listed in their pin function tables.
+pub trait PinFunction<F: Function> {
+    /// Selects `F` in the pin's IOCON register.
+    fn select_function();
+}
+
+/// Implements `PinFunction` for the entries of a pin function table.
+///
+/// Each entry is the pin type, its IOCON register, the function and the FUNC value selecting it.
+macro_rules! pin_functions {
+    ($($(#[$attr: meta])* ($pin: ident, $io: ident, $function: ident, $func: ident)),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            impl<T> crate::gpio::PinFunction<crate::gpio::function::$function> for crate::gpio::$pin<T>
+            where
+                T: crate::typestates::PinState,
+            {
+                fn select_function() {
+                    unsafe {
+                        (*crate::pac::IOCON::ptr()).$io.modify(|_, w| w.func().$func());
+                    }
+                }
+            }
+        )*
+    }
+}
+
+pub(crate) use pin_functions;
+
+/// Implemented by the typed pins, giving their position in the port.
+pub trait PortPin<S: typestates::PinState> {
+    /// Number of the port.
+    const PORT: u8;
+    /// Number of the pin within the port.
+    const PIN: u8;
+
+    /// Erases the pin's number from its type, like `erase_pin`.
+    fn erase(self) -> Pin<S>;
+}
+
+/// Implemented by a typed pin for the pin one bit below it in the same port.
+///
+/// Used to check at compile time that the pins of a `ParallelPort` are consecutive.
+pub trait Follows<P> {}
+
+/// Pull resistor configuration of a pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pull {
+    /// Neither pull-up nor pull-down resistor enabled.
+    None,
+    /// Pull-down resistor enabled.
+    Down,
+    /// Pull-up resistor enabled.
+    Up,
+    /// Repeater mode, the pin keeps its last driven level when released.
+    Repeater,
+}
+
+/// Slew rate of a pin's output driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slew {
+    /// Standard slew rate, recommended for most uses.
+    Standard,
+    /// Fast slew rate, for signals above 10 MHz.
+    Fast,
+}
+
+/// Logic level of a pin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Low,
+    High,
+}
+
+/// Signal edges triggering a GPIO interrupt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    RisingFalling,
+}
+
+/// Access to the IOCON register of a pin.
+///
+/// The IOCON registers of all pins are laid out as one array of 32 registers per port.
+/// Depending on the pin, the register is of type D, A, W, I or U, which support different
+/// subsets of the configuration bits. Writes to bits the pin's type lacks are dropped.
+mod iocon {
+    use super::{Pull, Slew};
+    use crate::pac;
+    use core::ptr;
+
+    const FUNC: u32 = 0b111;
+    const MODE: u32 = 0b11 << 3;
+    const HYS: u32 = 1 << 5;
+    const INV: u32 = 1 << 6;
+    const ADMODE: u32 = 1 << 7;
+    const FILTER: u32 = 1 << 8;
+    const SLEW: u32 = 1 << 9;
+    const OD: u32 = 1 << 10;
+    const DACEN: u32 = 1 << 16;
+
+    fn register(port: u8, pin: u8) -> *mut u32 {
+        unsafe { (pac::IOCON::ptr() as *mut u32).add(port as usize * 32 + pin as usize) }
+    }
+
+    /// IOCON register types.
+    enum Type {
+        /// Digital pins
+        D,
+        /// Analog pins
+        A,
+        /// Digital pins with analog filter
+        W,
+        /// I2C pins
+        I,
+        /// USB pins
+        U,
+    }
+
+    fn pin_type(port: u8, pin: u8) -> Type {
+        match (port, pin) {
+            (0, 27) | (0, 28) | (5, 2) | (5, 3) => Type::I,
+            (0, 29) | (0, 30) | (0, 31) => Type::U,
+            (0, 12) | (0, 13) | (0, 23..=26) | (1, 30) | (1, 31) => Type::A,
+            (0, 7) | (0, 8) | (0, 9) => Type::W,
+            _ => Type::D,
+        }
+    }
+
+    fn writable_bits(port: u8, pin: u8) -> u32 {
+        match pin_type(port, pin) {
+            Type::I => FUNC | INV,
+            Type::U => FUNC,
+            Type::A => FUNC | MODE | INV | ADMODE | FILTER | OD | DACEN,
+            Type::W => FUNC | MODE | HYS | INV | FILTER | SLEW | OD,
+            Type::D => FUNC | MODE | HYS | INV | SLEW | OD,
+        }
+    }
+
+    /// Returns the register value after reset: GPIO function, pull-up resistor and, where
+    /// available, hysteresis, digital mode and disabled filter.
+    fn reset_value(port: u8, pin: u8) -> u32 {
+        const PULL_UP: u32 = 0b10 << 3;
+        match pin_type(port, pin) {
+            Type::I | Type::U => 0,
+            Type::A => PULL_UP | ADMODE | FILTER,
+            Type::W => PULL_UP | HYS | FILTER,
+            Type::D => PULL_UP | HYS,
+        }
+    }
+
+    fn modify(port: u8, pin: u8, mask: u32, value: u32) {
+        let mask = mask & writable_bits(port, pin);
+        let register = register(port, pin);
+        unsafe {
+            ptr::write_volatile(register, (ptr::read_volatile(register) & !mask) | (value & mask));
+        }
+    }
+
+    fn flag(enabled: bool, bit: u32) -> u32 {
+        if enabled {
+            bit
+        } else {
+            0
+        }
+    }
+
+    pub(super) fn reset(port: u8, pin: u8) {
+        modify(port, pin, !0, reset_value(port, pin));
+    }
+
+    pub(super) fn select_gpio(port: u8, pin: u8) {
+        select_digital(port, pin);
+        modify(port, pin, FUNC, 0);
+    }
+
+    /// Enables the digital input buffer of the pin and disables the DAC output.
+    pub(super) fn select_digital(port: u8, pin: u8) {
+        modify(port, pin, ADMODE | DACEN, ADMODE);
+    }
+
+    /// Disables the digital input buffer and the pull resistors of the pin.
+    pub(super) fn select_analog(port: u8, pin: u8) {
+        modify(port, pin, ADMODE | MODE, 0);
+    }
+
+    pub(super) fn set_dac_enabled(port: u8, pin: u8, enabled: bool) {
+        modify(port, pin, DACEN, flag(enabled, DACEN));
+    }
+
+    pub(super) fn set_pull(port: u8, pin: u8, pull: Pull) {
+        let mode = match pull {
+            Pull::None => 0b00,
+            Pull::Down => 0b01,
+            Pull::Up => 0b10,
+            Pull::Repeater => 0b11,
+        };
+        modify(port, pin, MODE, mode << 3);
+    }
+
+    pub(super) fn set_hysteresis(port: u8, pin: u8, enabled: bool) {
+        modify(port, pin, HYS, flag(enabled, HYS));
+    }
+
+    pub(super) fn set_input_inverted(port: u8, pin: u8, inverted: bool) {
+        modify(port, pin, INV, flag(inverted, INV));
+    }
+
+    /// Returns whether the pin can be an open-drain output. I2C pins always are, USB pins never.
+    pub(super) fn has_open_drain(port: u8, pin: u8) -> bool {
+        !matches!(pin_type(port, pin), Type::U)
+    }
+
+    pub(super) fn set_open_drain(port: u8, pin: u8, enabled: bool) {
+        modify(port, pin, OD, flag(enabled, OD));
+    }
+
+    pub(super) fn set_slew(port: u8, pin: u8, slew: Slew) {
+        modify(port, pin, SLEW, flag(slew == Slew::Fast, SLEW));
+    }
+}
+
+/// Access to the GPIO registers of a port.
+///
+/// The registers of all ports are laid out as one register block of eight words per port, so
+/// the erased `Pin` can look them up by port number.
+mod port {
+    use crate::pac;
+    use core::ptr;
+
+    pub(super) const DIR: usize = 0;
+    pub(super) const MASK: usize = 4;
+    pub(super) const PIN: usize = 5;
+    pub(super) const SET: usize = 6;
+    pub(super) const CLR: usize = 7;
+
+    fn register(port: u8, offset: usize) -> *mut u32 {
+        unsafe { (pac::GPIO::ptr() as *mut u32).add(port as usize * 8 + offset) }
+    }
+
+    pub(super) fn read(port: u8, offset: usize) -> u32 {
+        unsafe { ptr::read_volatile(register(port, offset)) }
+    }
+
+    pub(super) fn write(port: u8, offset: usize, value: u32) {
+        unsafe { ptr::write_volatile(register(port, offset), value) }
+    }
+
+    /// Writes byte `lane` of a register, e.g. FIOxPIN2 for lane 2 of PIN.
+    pub(super) fn write_u8(port: u8, offset: usize, lane: u8, value: u8) {
+        unsafe { ptr::write_volatile((register(port, offset) as *mut u8).add(lane as usize), value) }
+    }
+
+    /// Writes halfword `lane` of a register, e.g. FIOxPINH for lane 1 of PIN.
+    pub(super) fn write_u16(port: u8, offset: usize, lane: u8, value: u16) {
+        unsafe { ptr::write_volatile((register(port, offset) as *mut u16).add(lane as usize), value) }
+    }
+
+    pub(super) fn set_direction(port: u8, pin: u8, output: bool) {
+        let dir = read(port, DIR);
+        if output {
+            write(port, DIR, dir | (1 << pin));
+        } else {
+            write(port, DIR, dir & !(1 << pin));
+        }
+    }
+}
 
 macro_rules! gpio {
-    ($GPIO: ident, $mod: ident, [$(($PIN: ident, $pin: ident)),+ $(,)?]) => {
+    ($GPIO: ident, $mod: ident, [$($(#[$attr: meta])* ($PIN: ident, $pin: ident)),+ $(,)?]) => {
         pub mod $mod {
 
         use super::*;
@@ -17,19 +293,22 @@ macro_rules! gpio {
         #[allow(dead_code)]
         pub struct Target {
             $(
+                $(#[$attr])*
                 pub $pin: $PIN<typestates::Gpio<direction::Unknown>>,
             )+
         }
 
         impl $GPIO {
 
-            pub fn new() -> Self {
+            /// Only `Hal` creates the ports, so each pin has a single owner.
+            pub(crate) fn new() -> Self {
                 $GPIO{}
             }
 
             pub fn split(self) -> Target {
                 Target{
                     $(
+                        $(#[$attr])*
                         $pin: $PIN{ _p: PhantomData },
                     )+
                 }
@@ -56,8 +335,11 @@ gpio!(
         (P0_9, p0_9),
         (P0_10, p0_10),
         (P0_11, p0_11),
+        #[cfg(not(feature = "lqfp144"))]
         (P0_12, p0_12),
+        #[cfg(not(feature = "lqfp144"))]
         (P0_13, p0_13),
+        #[cfg(not(feature = "lqfp144"))]
         (P0_14, p0_14),
         (P0_15, p0_15),
         (P0_16, p0_16),
@@ -85,17 +367,25 @@ gpio!(
     [
         (P1_0, p1_0),
         (P1_1, p1_1),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_2, p1_2),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_3, p1_3),
         (P1_4, p1_4),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_5, p1_5),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_6, p1_6),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_7, p1_7),
         (P1_8, p1_8),
         (P1_9, p1_9),
         (P1_10, p1_10),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_11, p1_11),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_12, p1_12),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_13, p1_13),
         (P1_14, p1_14),
         (P1_15, p1_15),
@@ -136,23 +426,35 @@ gpio!(
         (P2_11, p2_11),
         (P2_12, p2_12),
         (P2_13, p2_13),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_14, p2_14),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_15, p2_15),
         (P2_16, p2_16),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_17, p2_17),
         (P2_18, p2_18),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_19, p2_19),
         (P2_20, p2_20),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_21, p2_21),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_22, p2_22),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_23, p2_23),
         (P2_24, p2_24),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_25, p2_25),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_26, p2_26),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_27, p2_27),
         (P2_28, p2_28),
         (P2_29, p2_29),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_30, p2_30),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_31, p2_31),
     ]
 );
@@ -177,21 +479,37 @@ gpio!(
         (P3_13, p3_13),
         (P3_14, p3_14),
         (P3_15, p3_15),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_16, p3_16),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_17, p3_17),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_18, p3_18),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_19, p3_19),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_20, p3_20),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_21, p3_21),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_22, p3_22),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_23, p3_23),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_24, p3_24),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_25, p3_25),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_26, p3_26),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_27, p3_27),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_28, p3_28),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_29, p3_29),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_30, p3_30),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_31, p3_31),
     ]
 );
@@ -216,25 +534,39 @@ gpio!(
         (P4_13, p4_13),
         (P4_14, p4_14),
         (P4_15, p4_15),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_16, p4_16),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_17, p4_17),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_18, p4_18),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_19, p4_19),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_20, p4_20),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_21, p4_21),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_22, p4_22),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_23, p4_23),
         (P4_24, p4_24),
         (P4_25, p4_25),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_26, p4_26),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_27, p4_27),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_28, p4_28),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_29, p4_29),
         (P4_30, p4_30),
         (P4_31, p4_31),
     ]
 );
 
+// Port 5 is not bonded out on LQFP144
+#[cfg(not(feature = "lqfp144"))]
 gpio!(
     GPIO5,
     gpio5,
@@ -248,12 +580,14 @@ gpio!(
 );
 
 macro_rules! pins {
-    ($clr: ident, $set: ident, $pin: ident, $dir: ident, [$(($type: ident, $port: expr, $pin_no: expr)),+ $(,)?]) => {
+    ($clr: ident, $set: ident, $pin: ident, $dir: ident, [$($(#[$attr: meta])* ($type: ident, $port: expr, $pin_no: expr $(, $driver: ident)?)),+ $(,)?]) => {
         $(
+        $(#[$attr])*
         pub struct $type<S: typestates::PinState> {
             _p: PhantomData<S>,
         }
 
+        $(#[$attr])*
         impl<S> $type<S>
         where
             S: typestates::PinState
@@ -261,8 +595,107 @@ macro_rules! pins {
             pub fn erase_pin(self) -> Pin<S> {
                 Pin{ port: $port, pin: $pin_no, _p: PhantomData }
             }
+
+            /// Configures the pin as push-pull output.
+            pub fn into_output(self) -> $type<typestates::Gpio<direction::Output>> {
+                iocon::select_gpio($port, $pin_no);
+                iocon::set_open_drain($port, $pin_no, false);
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() | (1 << $pin_no))); };
+                $type::<typestates::Gpio<direction::Output>>{ _p: PhantomData }
+            }
+
+            /// Configures the pin as output, driving `level` from the first moment on.
+            ///
+            /// The output register is written before the pin's direction is switched, so the pin
+            /// never drives a previous output level.
+            pub fn into_output_in_state(self, level: Level) -> $type<typestates::Gpio<direction::Output>> {
+                match level {
+                    Level::Low => unsafe { (*pac::GPIO::ptr()).$clr.write(|w| w.bits(1 << $pin_no)); },
+                    Level::High => unsafe { (*pac::GPIO::ptr()).$set.write(|w| w.bits(1 << $pin_no)); },
+                }
+                self.into_output()
+            }
+
+            pub fn into_input(self) -> $type<typestates::Gpio<direction::Input>> {
+                iocon::select_gpio($port, $pin_no);
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no))); };
+                $type::<typestates::Gpio<direction::Input>>{ _p: PhantomData }
+            }
+
+            /// Configures the pin as GPIO whose direction can be changed at runtime.
+            ///
+            /// The pin starts out as input and drives its output push-pull.
+            pub fn into_dynamic(self) -> $type<typestates::Gpio<direction::Dynamic>> {
+                iocon::select_gpio($port, $pin_no);
+                iocon::set_open_drain($port, $pin_no, false);
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no))); };
+                $type::<typestates::Gpio<direction::Dynamic>>{ _p: PhantomData }
+            }
+
+            /// Returns the pin to its state after reset, e.g. when the peripheral using it is torn down.
+            ///
+            /// The IOCON register gets its reset value, which selects the GPIO function, and the pin
+            /// is switched to input.
+            pub fn into_reset_state(self) -> $type<typestates::Gpio<direction::Unknown>> {
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no))); };
+                iocon::reset($port, $pin_no);
+                $type::<typestates::Gpio<direction::Unknown>>{ _p: PhantomData }
+            }
+
+            /// Connects the pin to the peripheral function `F`.
+            ///
+            /// # Example
+            /// ```
+            /// let pins = hal.gpio0.split();
+            /// let tx = pins.p0_2.into_function::<gpio::function::U0Txd>();
+            /// ```
+            pub fn into_function<F>(self) -> $type<typestates::AltFunction<F>>
+            where
+                F: Function,
+                Self: PinFunction<F>,
+            {
+                iocon::select_digital($port, $pin_no);
+                <Self as PinFunction<F>>::select_function();
+                $type::<typestates::AltFunction<F>>{ _p: PhantomData }
+            }
+
+            /// Selects the pull resistor of the pin.
+            ///
+            /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3 and on the USB pins P0_29
+            /// to P0_31, which have no pull resistors.
+            pub fn set_pull(&mut self, pull: Pull) {
+                iocon::set_pull($port, $pin_no, pull);
+            }
+
+            /// Enables or disables the input hysteresis of the pin.
+            ///
+            /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3, on the USB pins P0_29 to
+            /// P0_31 and on the analog pins P0_12, P0_13, P0_23 to P0_26, P1_30 and P1_31, which
+            /// have no hysteresis control.
+            pub fn set_hysteresis(&mut self, enabled: bool) {
+                iocon::set_hysteresis($port, $pin_no, enabled);
+            }
+
+            /// Enables or disables the inversion of the pin's input level.
+            ///
+            /// Has no effect on the USB pins P0_29 to P0_31, which can not invert their input.
+            pub fn set_input_inverted(&mut self, inverted: bool) {
+                iocon::set_input_inverted($port, $pin_no, inverted);
+            }
+
+            /// Selects the slew rate of the pin's output driver.
+            ///
+            /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3, on the USB pins P0_29 to
+            /// P0_31 and on the analog pins P0_12, P0_13, P0_23 to P0_26, P1_30 and P1_31, which
+            /// have no slew rate control.
+            pub fn set_slew(&mut self, slew: Slew) {
+                iocon::set_slew($port, $pin_no, slew);
+            }
         }
 
+        open_drain!($(#[$attr])* ($type, $port, $pin_no, $clr, $set, $pin, $dir) $($driver)?);
+
+        $(#[$attr])*
         impl OutputPin for $type<typestates::Gpio<direction::Output>> {
             type Error = ();
 
@@ -275,6 +708,22 @@ macro_rules! pins {
             }
         }
 
+        $(#[$attr])*
+        impl StatefulOutputPin for $type<typestates::Gpio<direction::Output>> {
+            fn try_is_set_high(&self) -> Result<bool, Self::Error> {
+                // Reading SET returns the port's output register
+                Ok(unsafe { ((*pac::GPIO::ptr()).$set.read().bits() & (1 << $pin_no)) != 0 })
+            }
+
+            fn try_is_set_low(&self) -> Result<bool, Self::Error> {
+                self.try_is_set_high().map(|v| !v)
+            }
+        }
+
+        $(#[$attr])*
+        impl toggleable::Default for $type<typestates::Gpio<direction::Output>> {}
+
+        $(#[$attr])*
         impl InputPin for $type<typestates::Gpio<direction::Input>> {
             type Error = ();
 
@@ -287,24 +736,141 @@ macro_rules! pins {
             }
         }
 
-        impl<D> $type<typestates::Gpio<D>>
-            where
-                D: direction::Direction
-        {
-            pub fn into_output(self) -> $type<typestates::Gpio<direction::Output>> {
+        $(#[$attr])*
+        impl $type<typestates::Gpio<direction::Dynamic>> {
+            /// Switches the pin to input.
+            pub fn set_as_input(&mut self) {
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no))); };
+            }
+
+            /// Switches the pin to output, driving the level last set with `OutputPin`.
+            pub fn set_as_output(&mut self) {
                 unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() | (1 << $pin_no))); };
-                $type::<typestates::Gpio<direction::Output>>{ _p: PhantomData }
             }
 
-            pub fn into_input(self) -> $type<typestates::Gpio<direction::Input>> {
-                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no))); };
-                $type::<typestates::Gpio<direction::Input>>{ _p: PhantomData }
+            /// Returns whether the pin is currently an output.
+            pub fn is_output(&self) -> bool {
+                unsafe { ((*pac::GPIO::ptr()).$dir.read().bits() & (1 << $pin_no)) != 0 }
+            }
+        }
+
+        $(#[$attr])*
+        impl OutputPin for $type<typestates::Gpio<direction::Dynamic>> {
+            type Error = ();
+
+            fn try_set_low(&mut self) -> Result<(), Self::Error> {
+                Ok(unsafe { (*pac::GPIO::ptr()).$clr.write(|w| w.bits(1 << $pin_no)); })
+            }
+
+            fn try_set_high(&mut self) -> Result<(), Self::Error> {
+                Ok(unsafe { (*pac::GPIO::ptr()).$set.write(|w| w.bits(1 << $pin_no)); })
+            }
+        }
+
+        $(#[$attr])*
+        impl InputPin for $type<typestates::Gpio<direction::Dynamic>> {
+            type Error = ();
+
+            fn try_is_high(&self) -> Result<bool, Self::Error> {
+                self.try_is_low().map(|v| !v)
+            }
+
+            fn try_is_low(&self) -> Result<bool, Self::Error> {
+                Ok(unsafe { ((*pac::GPIO::ptr()).$pin.read().bits() & (1 << $pin_no)) == 0 })
             }
         }
+
+        $(#[$attr])*
+        impl<S> PortPin<S> for $type<S>
+        where
+            S: typestates::PinState
+        {
+            const PORT: u8 = $port;
+            const PIN: u8 = $pin_no;
+
+            fn erase(self) -> Pin<S> {
+                self.erase_pin()
+            }
+        }
+
         )+
+
+        follows!($($(#[$attr])* $type),+);
     }
 }
 
+/// Adds the open-drain mode to a pin, unless it is marked as `push_pull` in its `pins!` table.
+macro_rules! open_drain {
+    ($(#[$attr: meta])* ($type: ident, $port: expr, $pin_no: expr, $clr: ident, $set: ident, $pin: ident, $dir: ident) push_pull) => {};
+    ($(#[$attr: meta])* ($type: ident, $port: expr, $pin_no: expr, $clr: ident, $set: ident, $pin: ident, $dir: ident)) => {
+        $(#[$attr])*
+        impl<S> $type<S>
+        where
+            S: typestates::PinState
+        {
+            /// Configures the pin as open-drain output.
+            ///
+            /// Setting the pin high releases the line, so its level can be read back as input.
+            /// The line is released before the pin becomes an output, so it is not pulled low.
+            pub fn into_open_drain_output(self) -> $type<typestates::Gpio<direction::OpenDrain>> {
+                iocon::select_gpio($port, $pin_no);
+                iocon::set_open_drain($port, $pin_no, true);
+                unsafe { (*pac::GPIO::ptr()).$set.write(|w| w.bits(1 << $pin_no)); };
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() | (1 << $pin_no))); };
+                $type::<typestates::Gpio<direction::OpenDrain>>{ _p: PhantomData }
+            }
+
+            /// Enables or disables the open-drain mode of the pin's output driver.
+            ///
+            /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3, which are always
+            /// open-drain.
+            pub fn set_open_drain(&mut self, enabled: bool) {
+                iocon::set_open_drain($port, $pin_no, enabled);
+            }
+        }
+
+        $(#[$attr])*
+        impl OutputPin for $type<typestates::Gpio<direction::OpenDrain>> {
+            type Error = ();
+
+            fn try_set_low(&mut self) -> Result<(), Self::Error> {
+                Ok(unsafe { (*pac::GPIO::ptr()).$clr.write(|w| w.bits(1 << $pin_no)); })
+            }
+
+            fn try_set_high(&mut self) -> Result<(), Self::Error> {
+                Ok(unsafe { (*pac::GPIO::ptr()).$set.write(|w| w.bits(1 << $pin_no)); })
+            }
+        }
+
+        $(#[$attr])*
+        impl InputPin for $type<typestates::Gpio<direction::OpenDrain>> {
+            type Error = ();
+
+            fn try_is_high(&self) -> Result<bool, Self::Error> {
+                self.try_is_low().map(|v| !v)
+            }
+
+            fn try_is_low(&self) -> Result<bool, Self::Error> {
+                Ok(unsafe { ((*pac::GPIO::ptr()).$pin.read().bits() & (1 << $pin_no)) == 0 })
+            }
+        }
+    };
+}
+
+/// Implements `Follows` for every pair of neighbouring pins in the list.
+///
+/// Pins that are not bonded out break the chain, so a `ParallelPort` can not span them.
+macro_rules! follows {
+    ($(#[$attr: meta])* $type: ident) => {};
+    ($(#[$attr: meta])* $type: ident, $(#[$next_attr: meta])* $next: ident $(, $($rest: tt)*)?) => {
+        $(#[$attr])*
+        $(#[$next_attr])*
+        impl<S> Follows<$type<S>> for $next<S> where S: typestates::PinState {}
+
+        follows!($(#[$next_attr])* $next $(, $($rest)*)?);
+    };
+}
+
 pins!(
     clr0,
     set0,
@@ -323,8 +889,11 @@ pins!(
         (P0_9, 0, 9),
         (P0_10, 0, 10),
         (P0_11, 0, 11),
+        #[cfg(not(feature = "lqfp144"))]
         (P0_12, 0, 12),
+        #[cfg(not(feature = "lqfp144"))]
         (P0_13, 0, 13),
+        #[cfg(not(feature = "lqfp144"))]
         (P0_14, 0, 14),
         (P0_15, 0, 15),
         (P0_16, 0, 16),
@@ -340,9 +909,10 @@ pins!(
         (P0_26, 0, 26),
         (P0_27, 0, 27),
         (P0_28, 0, 28),
-        (P0_29, 0, 29),
-        (P0_30, 0, 30),
-        (P0_31, 0, 31),
+        // USB pins have no open-drain mode
+        (P0_29, 0, 29, push_pull),
+        (P0_30, 0, 30, push_pull),
+        (P0_31, 0, 31, push_pull),
     ]
 );
 
@@ -354,17 +924,25 @@ pins!(
     [
         (P1_0, 1, 0),
         (P1_1, 1, 1),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_2, 1, 2),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_3, 1, 3),
         (P1_4, 1, 4),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_5, 1, 5),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_6, 1, 6),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_7, 1, 7),
         (P1_8, 1, 8),
         (P1_9, 1, 9),
         (P1_10, 1, 10),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_11, 1, 11),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_12, 1, 12),
+        #[cfg(not(feature = "lqfp144"))]
         (P1_13, 1, 13),
         (P1_14, 1, 14),
         (P1_15, 1, 15),
@@ -407,23 +985,35 @@ pins!(
         (P2_11, 2, 11),
         (P2_12, 2, 12),
         (P2_13, 2, 13),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_14, 2, 14),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_15, 2, 15),
         (P2_16, 2, 16),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_17, 2, 17),
         (P2_18, 2, 18),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_19, 2, 19),
         (P2_20, 2, 20),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_21, 2, 21),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_22, 2, 22),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_23, 2, 23),
         (P2_24, 2, 24),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_25, 2, 25),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_26, 2, 26),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_27, 2, 27),
         (P2_28, 2, 28),
         (P2_29, 2, 29),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_30, 2, 30),
+        #[cfg(not(feature = "lqfp144"))]
         (P2_31, 2, 31),
     ]
 );
@@ -450,21 +1040,37 @@ pins!(
         (P3_13, 3, 13),
         (P3_14, 3, 14),
         (P3_15, 3, 15),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_16, 3, 16),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_17, 3, 17),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_18, 3, 18),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_19, 3, 19),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_20, 3, 20),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_21, 3, 21),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_22, 3, 22),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_23, 3, 23),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_24, 3, 24),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_25, 3, 25),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_26, 3, 26),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_27, 3, 27),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_28, 3, 28),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_29, 3, 29),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_30, 3, 30),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P3_31, 3, 31),
     ]
 );
@@ -491,25 +1097,38 @@ pins!(
         (P4_13, 4, 13),
         (P4_14, 4, 14),
         (P4_15, 4, 15),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_16, 4, 16),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_17, 4, 17),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_18, 4, 18),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_19, 4, 19),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_20, 4, 20),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_21, 4, 21),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_22, 4, 22),
+        #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
         (P4_23, 4, 23),
         (P4_24, 4, 24),
         (P4_25, 4, 25),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_26, 4, 26),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_27, 4, 27),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_28, 4, 28),
+        #[cfg(not(feature = "lqfp144"))]
         (P4_29, 4, 29),
         (P4_30, 4, 30),
         (P4_31, 4, 31),
     ]
 );
 
+#[cfg(not(feature = "lqfp144"))]
 pins!(
     clr5,
     set5,
@@ -524,64 +1143,330 @@ pins!(
     ]
 );
 
+/// Implemented by pins in analog mode that are connected to an ADC input.
+pub trait AdcChannel {
+    /// Index of the ADC input channel.
+    const CHANNEL: u8;
+}
+
+macro_rules! analog_pins {
+    ($dir: ident, [$($(#[$attr: meta])* ($type: ident, $iocon: ident, $port: expr, $pin_no: expr, $func: ident, $channel: expr)),+ $(,)?]) => {
+        $(
+        $(#[$attr])*
+        impl<S> $type<S>
+        where
+            S: typestates::PinState
+        {
+            /// Configures the pin as analog input of the ADC.
+            ///
+            /// The digital input buffer and the pull resistors of the pin are disabled.
+            pub fn into_analog(self) -> $type<typestates::Analog> {
+                unsafe { (*pac::GPIO::ptr()).$dir.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no))); };
+                iocon::set_dac_enabled($port, $pin_no, false);
+                iocon::select_analog($port, $pin_no);
+                unsafe { (*pac::IOCON::ptr()).$iocon.modify(|_, w| w.func().$func()); }
+                $type::<typestates::Analog>{ _p: PhantomData }
+            }
+        }
+
+        $(#[$attr])*
+        impl AdcChannel for $type<typestates::Analog> {
+            const CHANNEL: u8 = $channel;
+        }
+        )+
+    }
+}
+
+analog_pins!(
+    dir0,
+    [
+        (P0_23, p0_23, 0, 23, adc0_in0, 0),
+        (P0_24, p0_24, 0, 24, adc0_in1, 1),
+        (P0_25, p0_25, 0, 25, adc0_in2, 2),
+        (P0_26, p0_26, 0, 26, adc0_in3, 3),
+        #[cfg(not(feature = "lqfp144"))]
+        (P0_12, p0_12, 0, 12, adc0_in6, 6),
+        #[cfg(not(feature = "lqfp144"))]
+        (P0_13, p0_13, 0, 13, adc0_in7, 7),
+    ]
+);
+
+analog_pins!(
+    dir1,
+    [
+        (P1_30, p1_30, 1, 30, adc0_in4, 4),
+        (P1_31, p1_31, 1, 31, adc0_in5, 5),
+    ]
+);
+
+impl<S: typestates::PinState> PinFunction<function::DacOut> for P0_26<S> {
+    fn select_function() {
+        unsafe { (*pac::IOCON::ptr()).p0_26.modify(|_, w| w.func().dac_out()); }
+        iocon::select_analog(0, 26);
+        iocon::set_dac_enabled(0, 26, true);
+    }
+}
+
+macro_rules! gpio_interrupts {
+    ($enr: ident, $enf: ident, $statr: ident, $statf: ident, $clr: ident, [$($(#[$attr: meta])* ($type: ident, $pin_no: expr)),+ $(,)?]) => {
+        $(
+        $(#[$attr])*
+        impl $type<typestates::Gpio<direction::Input>> {
+            /// Enables the interrupt of the pin for `edge`.
+            ///
+            /// All pins of ports 0 and 2 share the GPIO interrupt, see `dispatch_interrupts`.
+            pub fn enable_interrupt(&mut self, edge: Edge) {
+                let (rising, falling) = match edge {
+                    Edge::Rising => (true, false),
+                    Edge::Falling => (false, true),
+                    Edge::RisingFalling => (true, true),
+                };
+                let gpioint = unsafe { &(*pac::GPIOINT::ptr()) };
+                unsafe {
+                    gpioint.$enr.modify(|r, w| w.bits(if rising { r.bits() | (1 << $pin_no) } else { r.bits() & !(1 << $pin_no) }));
+                    gpioint.$enf.modify(|r, w| w.bits(if falling { r.bits() | (1 << $pin_no) } else { r.bits() & !(1 << $pin_no) }));
+                }
+            }
+
+            /// Disables the interrupt of the pin for both edges.
+            pub fn disable_interrupt(&mut self) {
+                let gpioint = unsafe { &(*pac::GPIOINT::ptr()) };
+                unsafe {
+                    gpioint.$enr.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no)));
+                    gpioint.$enf.modify(|r, w| w.bits(r.bits() & !(1 << $pin_no)));
+                }
+            }
+
+            /// Returns whether an interrupt of the pin is pending.
+            pub fn check_interrupt(&self) -> bool {
+                let gpioint = unsafe { &(*pac::GPIOINT::ptr()) };
+                ((gpioint.$statr.read().bits() | gpioint.$statf.read().bits()) & (1 << $pin_no)) != 0
+            }
+
+            /// Clears the pending interrupt of the pin.
+            pub fn clear_interrupt_pending_bit(&mut self) {
+                unsafe { (*pac::GPIOINT::ptr()).$clr.write(|w| w.bits(1 << $pin_no)); }
+            }
+        }
+        )+
+    }
+}
+
+gpio_interrupts!(
+    enr0,
+    enf0,
+    statr0,
+    statf0,
+    clr0,
+    [
+        (P0_0, 0),
+        (P0_1, 1),
+        (P0_2, 2),
+        (P0_3, 3),
+        (P0_4, 4),
+        (P0_5, 5),
+        (P0_6, 6),
+        (P0_7, 7),
+        (P0_8, 8),
+        (P0_9, 9),
+        (P0_10, 10),
+        (P0_11, 11),
+        #[cfg(not(feature = "lqfp144"))]
+        (P0_12, 12),
+        #[cfg(not(feature = "lqfp144"))]
+        (P0_13, 13),
+        #[cfg(not(feature = "lqfp144"))]
+        (P0_14, 14),
+        (P0_15, 15),
+        (P0_16, 16),
+        (P0_17, 17),
+        (P0_18, 18),
+        (P0_19, 19),
+        (P0_20, 20),
+        (P0_21, 21),
+        (P0_22, 22),
+        (P0_23, 23),
+        (P0_24, 24),
+        (P0_25, 25),
+        (P0_26, 26),
+        (P0_27, 27),
+        (P0_28, 28),
+        (P0_29, 29),
+        (P0_30, 30),
+        (P0_31, 31),
+    ]
+);
+
+gpio_interrupts!(
+    enr2,
+    enf2,
+    statr2,
+    statf2,
+    clr2,
+    [
+        (P2_0, 0),
+        (P2_1, 1),
+        (P2_2, 2),
+        (P2_3, 3),
+        (P2_4, 4),
+        (P2_5, 5),
+        (P2_6, 6),
+        (P2_7, 7),
+        (P2_8, 8),
+        (P2_9, 9),
+        (P2_10, 10),
+        (P2_11, 11),
+        (P2_12, 12),
+        (P2_13, 13),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_14, 14),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_15, 15),
+        (P2_16, 16),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_17, 17),
+        (P2_18, 18),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_19, 19),
+        (P2_20, 20),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_21, 21),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_22, 22),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_23, 23),
+        (P2_24, 24),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_25, 25),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_26, 26),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_27, 27),
+        (P2_28, 28),
+        (P2_29, 29),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_30, 30),
+        #[cfg(not(feature = "lqfp144"))]
+        (P2_31, 31),
+    ]
+);
+
+/// Calls `handler` for every pending GPIO interrupt of ports 0 and 2 and clears it.
+///
+/// Ports 0 and 2 share a single GPIO interrupt vector, so this is meant to be called from its
+/// handler. If both edges of a pin are pending, `handler` is called once for each edge.
+///
+/// # Arguments
+///
+/// * handler - Called with the port number, pin number and edge of each pending interrupt.
+pub fn dispatch_interrupts<F>(mut handler: F)
+where
+    F: FnMut(u8, u8, Edge),
+{
+    let gpioint = unsafe { &(*pac::GPIOINT::ptr()) };
+    let pending = [
+        (0, gpioint.statr0.read().bits(), gpioint.statf0.read().bits()),
+        (2, gpioint.statr2.read().bits(), gpioint.statf2.read().bits()),
+    ];
+    unsafe {
+        gpioint.clr0.write(|w| w.bits(pending[0].1 | pending[0].2));
+        gpioint.clr2.write(|w| w.bits(pending[1].1 | pending[1].2));
+    }
+    for (port, rising, falling) in pending.iter() {
+        for pin in 0..32 {
+            if rising & (1 << pin) != 0 {
+                handler(*port, pin, Edge::Rising);
+            }
+            if falling & (1 << pin) != 0 {
+                handler(*port, pin, Edge::Falling);
+            }
+        }
+    }
+}
+
+/// A pin with its port and pin number stored at runtime, created with `erase_pin`.
+///
+/// Accesses compute the register address from the port number instead of using a constant,
+/// which takes a few more instructions than a typed pin but no branches.
 pub struct Pin<S: typestates::PinState> {
     port: u8,
     pin: u8,
     _p: PhantomData<S>,
 }
 
+impl<S> Pin<S>
+where
+    S: typestates::PinState,
+{
+    /// Selects the pull resistor of the pin.
+    ///
+    /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3 and on the USB pins P0_29 to
+    /// P0_31, which have no pull resistors.
+    pub fn set_pull(&mut self, pull: Pull) {
+        iocon::set_pull(self.port, self.pin, pull);
+    }
+
+    /// Enables or disables the input hysteresis of the pin.
+    ///
+    /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3, on the USB pins P0_29 to P0_31
+    /// and on the analog pins P0_12, P0_13, P0_23 to P0_26, P1_30 and P1_31, which have no
+    /// hysteresis control.
+    pub fn set_hysteresis(&mut self, enabled: bool) {
+        iocon::set_hysteresis(self.port, self.pin, enabled);
+    }
+
+    /// Enables or disables the inversion of the pin's input level.
+    ///
+    /// Has no effect on the USB pins P0_29 to P0_31, which can not invert their input.
+    pub fn set_input_inverted(&mut self, inverted: bool) {
+        iocon::set_input_inverted(self.port, self.pin, inverted);
+    }
+
+    /// Enables or disables the open-drain mode of the pin's output driver.
+    ///
+    /// Has no effect on the USB pins P0_29 to P0_31, which have no open-drain mode, and on the I2C
+    /// pins P0_27, P0_28, P5_2 and P5_3, which are always open-drain.
+    pub fn set_open_drain(&mut self, enabled: bool) {
+        iocon::set_open_drain(self.port, self.pin, enabled);
+    }
+
+    /// Selects the slew rate of the pin's output driver.
+    ///
+    /// Has no effect on the I2C pins P0_27, P0_28, P5_2 and P5_3, on the USB pins P0_29 to P0_31
+    /// and on the analog pins P0_12, P0_13, P0_23 to P0_26, P1_30 and P1_31, which have no slew
+    /// rate control.
+    pub fn set_slew(&mut self, slew: Slew) {
+        iocon::set_slew(self.port, self.pin, slew);
+    }
+}
+
 impl OutputPin for Pin<typestates::Gpio<direction::Output>> {
     type Error = ();
 
     fn try_set_low(&mut self) -> Result<(), Self::Error> {
-        match self.port {
-            0 => Ok(unsafe {
-                (*pac::GPIO::ptr()).clr0.write(|w| w.bits(1 << self.pin));
-            }),
-            1 => Ok(unsafe {
-                (*pac::GPIO::ptr()).clr1.write(|w| w.bits(1 << self.pin));
-            }),
-            2 => Ok(unsafe {
-                (*pac::GPIO::ptr()).clr2.write(|w| w.bits(1 << self.pin));
-            }),
-            3 => Ok(unsafe {
-                (*pac::GPIO::ptr()).clr3.write(|w| w.bits(1 << self.pin));
-            }),
-            4 => Ok(unsafe {
-                (*pac::GPIO::ptr()).clr4.write(|w| w.bits(1 << self.pin));
-            }),
-            5 => Ok(unsafe {
-                (*pac::GPIO::ptr()).clr5.write(|w| w.bits(1 << self.pin));
-            }),
-            _ => Err(()),
-        }
+        port::write(self.port, port::CLR, 1 << self.pin);
+        Ok(())
     }
 
     fn try_set_high(&mut self) -> Result<(), Self::Error> {
-        match self.port {
-            0 => Ok(unsafe {
-                (*pac::GPIO::ptr()).set0.write(|w| w.bits(1 << self.pin));
-            }),
-            1 => Ok(unsafe {
-                (*pac::GPIO::ptr()).set1.write(|w| w.bits(1 << self.pin));
-            }),
-            2 => Ok(unsafe {
-                (*pac::GPIO::ptr()).set2.write(|w| w.bits(1 << self.pin));
-            }),
-            3 => Ok(unsafe {
-                (*pac::GPIO::ptr()).set3.write(|w| w.bits(1 << self.pin));
-            }),
-            4 => Ok(unsafe {
-                (*pac::GPIO::ptr()).set4.write(|w| w.bits(1 << self.pin));
-            }),
-            5 => Ok(unsafe {
-                (*pac::GPIO::ptr()).set5.write(|w| w.bits(1 << self.pin));
-            }),
-            _ => Err(()),
-        }
+        port::write(self.port, port::SET, 1 << self.pin);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for Pin<typestates::Gpio<direction::Output>> {
+    fn try_is_set_high(&self) -> Result<bool, Self::Error> {
+        // Reading SET returns the port's output register
+        Ok((port::read(self.port, port::SET) & (1 << self.pin)) != 0)
+    }
+
+    fn try_is_set_low(&self) -> Result<bool, Self::Error> {
+        self.try_is_set_high().map(|v| !v)
     }
 }
 
+impl toggleable::Default for Pin<typestates::Gpio<direction::Output>> {}
+
 impl InputPin for Pin<typestates::Gpio<direction::Input>> {
     type Error = ();
 
@@ -590,56 +1475,85 @@ impl InputPin for Pin<typestates::Gpio<direction::Input>> {
     }
 
     fn try_is_low(&self) -> Result<bool, Self::Error> {
-        match self.port {
-            0 => Ok(unsafe { ((*pac::GPIO::ptr()).pin0.read().bits() & (1 << self.pin)) == 0 }),
-            1 => Ok(unsafe { ((*pac::GPIO::ptr()).pin1.read().bits() & (1 << self.pin)) == 0 }),
-            2 => Ok(unsafe { ((*pac::GPIO::ptr()).pin2.read().bits() & (1 << self.pin)) == 0 }),
-            3 => Ok(unsafe { ((*pac::GPIO::ptr()).pin3.read().bits() & (1 << self.pin)) == 0 }),
-            4 => Ok(unsafe { ((*pac::GPIO::ptr()).pin4.read().bits() & (1 << self.pin)) == 0 }),
-            5 => Ok(unsafe { ((*pac::GPIO::ptr()).pin5.read().bits() & (1 << self.pin)) == 0 }),
-            _ => Err(()),
-        }
+        Ok((port::read(self.port, port::PIN) & (1 << self.pin)) == 0)
+    }
+}
+
+impl OutputPin for Pin<typestates::Gpio<direction::OpenDrain>> {
+    type Error = ();
+
+    fn try_set_low(&mut self) -> Result<(), Self::Error> {
+        port::write(self.port, port::CLR, 1 << self.pin);
+        Ok(())
+    }
+
+    fn try_set_high(&mut self) -> Result<(), Self::Error> {
+        port::write(self.port, port::SET, 1 << self.pin);
+        Ok(())
+    }
+}
+
+impl InputPin for Pin<typestates::Gpio<direction::OpenDrain>> {
+    type Error = ();
+
+    fn try_is_high(&self) -> Result<bool, Self::Error> {
+        self.try_is_low().map(|v| !v)
+    }
+
+    fn try_is_low(&self) -> Result<bool, Self::Error> {
+        Ok((port::read(self.port, port::PIN) & (1 << self.pin)) == 0)
+    }
+}
+
+impl Pin<typestates::Gpio<direction::Dynamic>> {
+    /// Switches the pin to input.
+    pub fn set_as_input(&mut self) {
+        port::set_direction(self.port, self.pin, false);
+    }
+
+    /// Switches the pin to output, driving the level last set with `OutputPin`.
+    pub fn set_as_output(&mut self) {
+        port::set_direction(self.port, self.pin, true);
+    }
+
+    /// Returns whether the pin is currently an output.
+    pub fn is_output(&self) -> bool {
+        (port::read(self.port, port::DIR) & (1 << self.pin)) != 0
+    }
+}
+
+impl OutputPin for Pin<typestates::Gpio<direction::Dynamic>> {
+    type Error = ();
+
+    fn try_set_low(&mut self) -> Result<(), Self::Error> {
+        port::write(self.port, port::CLR, 1 << self.pin);
+        Ok(())
+    }
+
+    fn try_set_high(&mut self) -> Result<(), Self::Error> {
+        port::write(self.port, port::SET, 1 << self.pin);
+        Ok(())
     }
 }
 
-impl<D> Pin<typestates::Gpio<D>>
+impl InputPin for Pin<typestates::Gpio<direction::Dynamic>> {
+    type Error = ();
+
+    fn try_is_high(&self) -> Result<bool, Self::Error> {
+        self.try_is_low().map(|v| !v)
+    }
+
+    fn try_is_low(&self) -> Result<bool, Self::Error> {
+        Ok((port::read(self.port, port::PIN) & (1 << self.pin)) == 0)
+    }
+}
+
+impl<S> Pin<S>
 where
-    D: direction::Direction,
+    S: typestates::PinState,
 {
-    pub fn into_output(self) -> Pin<typestates::Gpio<direction::Output>> {
-        match self.port {
-            0 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir0
-                    .modify(|r, w| w.bits(r.bits() | (1 << self.pin)));
-            },
-            1 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir1
-                    .modify(|r, w| w.bits(r.bits() | (1 << self.pin)));
-            },
-            2 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir2
-                    .modify(|r, w| w.bits(r.bits() | (1 << self.pin)));
-            },
-            3 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir3
-                    .modify(|r, w| w.bits(r.bits() | (1 << self.pin)));
-            },
-            4 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir4
-                    .modify(|r, w| w.bits(r.bits() | (1 << self.pin)));
-            },
-            5 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir5
-                    .modify(|r, w| w.bits(r.bits() | (1 << self.pin)));
-            },
-            _ => (),
-        }
+    /// Returns a copy of the pin in state `T`, without changing its configuration.
+    fn with_state<T: typestates::PinState>(&self) -> Pin<T> {
         Pin {
             pin: self.pin,
             port: self.port,
@@ -647,44 +1561,298 @@ where
         }
     }
 
-    pub fn into_input(self) -> Pin<typestates::Gpio<direction::Input>> {
-        match self.port {
-            0 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir0
-                    .modify(|r, w| w.bits(r.bits() & !(1 << self.pin)));
-            },
-            1 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir1
-                    .modify(|r, w| w.bits(r.bits() & !(1 << self.pin)));
-            },
-            2 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir2
-                    .modify(|r, w| w.bits(r.bits() & !(1 << self.pin)));
-            },
-            3 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir3
-                    .modify(|r, w| w.bits(r.bits() & !(1 << self.pin)));
-            },
-            4 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir4
-                    .modify(|r, w| w.bits(r.bits() & !(1 << self.pin)));
-            },
-            5 => unsafe {
-                (*pac::GPIO::ptr())
-                    .dir5
-                    .modify(|r, w| w.bits(r.bits() & !(1 << self.pin)));
-            },
-            _ => (),
+    /// Configures the pin as output, driving `level` from the first moment on.
+    ///
+    /// The output register is written before the pin's direction is switched, so the pin
+    /// never drives a previous output level.
+    pub fn into_output_in_state(self, level: Level) -> Pin<typestates::Gpio<direction::Output>> {
+        match level {
+            Level::Low => port::write(self.port, port::CLR, 1 << self.pin),
+            Level::High => port::write(self.port, port::SET, 1 << self.pin),
         }
-        Pin {
-            pin: self.pin,
-            port: self.port,
+        self.into_output()
+    }
+
+    /// Configures the pin as push-pull output.
+    pub fn into_output(self) -> Pin<typestates::Gpio<direction::Output>> {
+        iocon::select_gpio(self.port, self.pin);
+        iocon::set_open_drain(self.port, self.pin, false);
+        port::set_direction(self.port, self.pin, true);
+        self.with_state()
+    }
+
+    /// Configures the pin as open-drain output.
+    ///
+    /// Setting the pin high releases the line, so its level can be read back as input.
+    /// The line is released before the pin becomes an output, so it is not pulled low.
+    ///
+    /// # Return
+    ///
+    /// * The open-drain pin, or the unchanged pin if it has no open-drain mode, which is the
+    ///   case for the USB pins P0_29 to P0_31.
+    pub fn into_open_drain_output(self) -> Result<Pin<typestates::Gpio<direction::OpenDrain>>, Self> {
+        if !iocon::has_open_drain(self.port, self.pin) {
+            return Err(self);
+        }
+        iocon::select_gpio(self.port, self.pin);
+        iocon::set_open_drain(self.port, self.pin, true);
+        port::write(self.port, port::SET, 1 << self.pin);
+        port::set_direction(self.port, self.pin, true);
+        Ok(self.with_state())
+    }
+
+    pub fn into_input(self) -> Pin<typestates::Gpio<direction::Input>> {
+        iocon::select_gpio(self.port, self.pin);
+        port::set_direction(self.port, self.pin, false);
+        self.with_state()
+    }
+
+    /// Configures the pin as GPIO whose direction can be changed at runtime.
+    ///
+    /// The pin starts out as input and drives its output push-pull.
+    pub fn into_dynamic(self) -> Pin<typestates::Gpio<direction::Dynamic>> {
+        iocon::select_gpio(self.port, self.pin);
+        iocon::set_open_drain(self.port, self.pin, false);
+        port::set_direction(self.port, self.pin, false);
+        self.with_state()
+    }
+
+    /// Returns the pin to its state after reset, e.g. when the peripheral using it is torn down.
+    ///
+    /// The IOCON register gets its reset value, which selects the GPIO function, and the pin is
+    /// switched to input.
+    pub fn into_reset_state(self) -> Pin<typestates::Gpio<direction::Unknown>> {
+        port::set_direction(self.port, self.pin, false);
+        iocon::reset(self.port, self.pin);
+        self.with_state()
+    }
+}
+
+/// Errors when creating a `ParallelPort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParallelPortError {
+    /// No pins were passed.
+    Empty,
+    /// The pins belong to different ports.
+    PortMismatch,
+    /// The pins are not consecutive pins of the port, in ascending order.
+    NotContiguous,
+}
+
+/// A group of contiguous pins of one port, accessed as a single word.
+///
+/// All pins of the group change in the same register access and the other pins of the port are
+/// unaffected. Groups filling a byte or halfword of the port, e.g. pins 8 to 15, are written
+/// through the byte (FIOxPIN0 to FIOxPIN3) or halfword (FIOxPINL, FIOxPINH) registers. Other
+/// groups are restricted through the port's MASK register.
+pub struct ParallelPort<S, P>
+where
+    S: typestates::PinState,
+    P: AsRef<[Pin<S>]>,
+{
+    pins: P,
+    port: u8,
+    offset: u8,
+    mask: u32,
+    _p: PhantomData<S>,
+}
+
+impl<S, P> ParallelPort<S, P>
+where
+    S: typestates::PinState,
+    P: AsRef<[Pin<S>]>,
+{
+    /// Creates a parallel port from erased pins.
+    ///
+    /// # Arguments
+    ///
+    /// * pins - Consecutive pins of one port in ascending order, the first pin is bit 0.
+    ///
+    /// # Example
+    /// ```
+    /// let pins = hal.gpio1.split();
+    /// let mut bus = ParallelPort::new([
+    ///     pins.p1_0.into_output().erase_pin(),
+    ///     pins.p1_1.into_output().erase_pin(),
+    ///     pins.p1_2.into_output().erase_pin(),
+    ///     pins.p1_3.into_output().erase_pin(),
+    /// ]).unwrap();
+    /// bus.write(0b1010);
+    /// ```
+    pub fn new(pins: P) -> Result<Self, ParallelPortError> {
+        let (port, offset, width) = {
+            let slice = pins.as_ref();
+            let first = slice.first().ok_or(ParallelPortError::Empty)?;
+            for (i, pin) in slice.iter().enumerate() {
+                if pin.port != first.port {
+                    return Err(ParallelPortError::PortMismatch);
+                }
+                if pin.pin as usize != first.pin as usize + i {
+                    return Err(ParallelPortError::NotContiguous);
+                }
+            }
+            (first.port, first.pin, slice.len() as u32)
+        };
+        Ok(Self::with_position(pins, port, offset, width))
+    }
+
+    /// Creates a parallel port from typed pins, checking at compile time that they are
+    /// consecutive pins of one port.
+    ///
+    /// # Arguments
+    ///
+    /// * pins - A tuple of up to 16 typed pins in ascending order, the first pin is bit 0.
+    ///
+    /// # Example
+    /// ```
+    /// let pins = hal.gpio2.split();
+    /// let mut bus = ParallelPort::from_pins((
+    ///     pins.p2_0.into_output(),
+    ///     pins.p2_1.into_output(),
+    ///     pins.p2_2.into_output(),
+    ///     pins.p2_3.into_output(),
+    /// ));
+    /// bus.write(0b1010);
+    /// ```
+    pub fn from_pins<T>(pins: T) -> Self
+    where
+        T: ParallelPins<S, Pins = P>,
+    {
+        Self::with_position(pins.erase(), T::PORT, T::OFFSET, T::WIDTH)
+    }
+
+    fn with_position(pins: P, port: u8, offset: u8, width: u32) -> Self {
+        let mask = if width == 32 { !0 } else { ((1 << width) - 1) << offset };
+        ParallelPort {
+            pins,
+            port,
+            offset,
+            mask,
             _p: PhantomData,
         }
     }
+
+    /// Returns the number of pins of the port.
+    pub fn width(&self) -> usize {
+        self.pins.as_ref().len()
+    }
+
+    /// Releases the pins.
+    pub fn free(self) -> P {
+        self.pins
+    }
+
+    fn read_pins(&self) -> u32 {
+        let value = port::read(self.port, port::PIN);
+        (value & self.mask) >> self.offset
+    }
+}
+
+impl<P> ParallelPort<typestates::Gpio<direction::Output>, P>
+where
+    P: AsRef<[Pin<typestates::Gpio<direction::Output>>]>,
+{
+    /// Drives `value` on the pins, bit 0 on the first pin.
+    ///
+    /// Bits above the port's width are ignored. Groups not filling a byte or halfword of the
+    /// port are written through the MASK register. Interrupts are disabled while it is set, as
+    /// it also restricts SET and CLR writes to other pins of the port. The previous MASK value
+    /// is restored afterwards.
+    pub fn write(&mut self, value: u32) {
+        match (self.width(), self.offset) {
+            (32, _) => port::write(self.port, port::PIN, value),
+            (16, offset) if offset % 16 == 0 => {
+                port::write_u16(self.port, port::PIN, offset / 16, value as u16)
+            }
+            (8, offset) if offset % 8 == 0 => {
+                port::write_u8(self.port, port::PIN, offset / 8, value as u8)
+            }
+            _ => {
+                let value = value << self.offset;
+                cortex_m::interrupt::free(|_| {
+                    let mask = port::read(self.port, port::MASK);
+                    port::write(self.port, port::MASK, !self.mask);
+                    port::write(self.port, port::PIN, value);
+                    port::write(self.port, port::MASK, mask);
+                });
+            }
+        }
+    }
+
+    /// Returns the value currently driven on the pins.
+    pub fn read_output(&self) -> u32 {
+        self.read_pins()
+    }
+}
+
+impl<P> ParallelPort<typestates::Gpio<direction::Input>, P>
+where
+    P: AsRef<[Pin<typestates::Gpio<direction::Input>>]>,
+{
+    /// Reads the levels of all pins at once, bit 0 from the first pin.
+    pub fn read(&self) -> u32 {
+        self.read_pins()
+    }
+}
+
+/// Tuples of typed pins that form a `ParallelPort`.
+///
+/// Implemented for tuples of 1 to 16 consecutive pins of one port in ascending order.
+pub trait ParallelPins<S: typestates::PinState> {
+    /// Number of the port.
+    const PORT: u8;
+    /// Number of the first pin within the port.
+    const OFFSET: u8;
+    /// Number of pins.
+    const WIDTH: u32;
+
+    /// The erased pins.
+    type Pins: AsRef<[Pin<S>]>;
+
+    /// Erases the pins' numbers from their types.
+    fn erase(self) -> Self::Pins;
 }
+
+macro_rules! parallel_pins {
+    ($(($width: expr; $first: ident $(, $next: ident: $previous: ident)*)),+ $(,)?) => {
+        $(
+            impl<S, $first $(, $next)*> ParallelPins<S> for ($first, $($next,)*)
+            where
+                S: typestates::PinState,
+                $first: PortPin<S>,
+                $($next: PortPin<S> + Follows<$previous>,)*
+            {
+                const PORT: u8 = $first::PORT;
+                const OFFSET: u8 = $first::PIN;
+                const WIDTH: u32 = $width;
+
+                type Pins = [Pin<S>; $width];
+
+                #[allow(non_snake_case)]
+                fn erase(self) -> Self::Pins {
+                    let ($first, $($next,)*) = self;
+                    [$first.erase() $(, $next.erase())*]
+                }
+            }
+        )+
+    }
+}
+
+parallel_pins!(
+    (1; A),
+    (2; A, B: A),
+    (3; A, B: A, C: B),
+    (4; A, B: A, C: B, D: C),
+    (5; A, B: A, C: B, D: C, E: D),
+    (6; A, B: A, C: B, D: C, E: D, F: E),
+    (7; A, B: A, C: B, D: C, E: D, F: E, G: F),
+    (8; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G),
+    (9; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H),
+    (10; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I),
+    (11; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I, K: J),
+    (12; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I, K: J, L: K),
+    (13; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I, K: J, L: K, M: L),
+    (14; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I, K: J, L: K, M: L, N: M),
+    (15; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I, K: J, L: K, M: L, N: M, O: N),
+    (16; A, B: A, C: B, D: C, E: D, F: E, G: F, H: G, I: H, J: I, K: J, L: K, M: L, N: M, O: N, P: O),
+);
@@ -0,0 +1,251 @@
+//! Debouncing of push-buttons and other mechanical contacts.
+//!
+//! `Debouncer` is a state machine fed with one raw sample per tick. It does not touch any
+//! hardware and can be used on its own. `Button` combines it with an input pin, and `poll`
+//! samples a set of buttons on every period of a periodic timer.
+//!
+//! # Example
+//! ```
+//! let mut timer = hal.timer0.enable(&clock).into_periodic();
+//! timer.try_start(1_000.microseconds()).unwrap();
+//!
+//! let pins = hal.gpio2.split();
+//! let mut buttons = [
+//!     Button::new(pins.p2_10.into_input().erase_pin(), Level::Low, Debouncer::new(20, Some(1_000))),
+//!     Button::new(pins.p2_11.into_input().erase_pin(), Level::Low, Debouncer::new(20, None)),
+//! ];
+//!
+//! // In the timer's interrupt handler
+//! debounce::poll(&mut timer, &mut buttons, |index, event| match event {
+//!     Event::Pressed => { /* ... */ }
+//!     Event::Released => { /* ... */ }
+//!     Event::LongPress => { /* ... */ }
+//! });
+//! ```
+
+use super::Level;
+use embedded_hal::digital::InputPin;
+use embedded_hal::timer::{CountDown, Periodic};
+
+/// Changes of a debounced input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The input became active.
+    Pressed,
+    /// The input became inactive.
+    Released,
+    /// The input has been active for the long press time, reported once per press.
+    LongPress,
+}
+
+/// Debouncing state machine of a single input.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    debounce_ticks: u16,
+    long_press_ticks: Option<u32>,
+    pressed: bool,
+    count: u16,
+    held: u32,
+}
+
+impl Debouncer {
+    /// Creates a debouncer for an inactive input.
+    ///
+    /// # Arguments
+    ///
+    /// * debounce_ticks - Number of consecutive samples at the new level before a change is reported.
+    ///   0 behaves like 1, every change is reported with the first sample at the new level.
+    /// * long_press_ticks - Number of ticks after `Pressed` until `LongPress` is reported,
+    ///   `None` disables long presses.
+    pub const fn new(debounce_ticks: u16, long_press_ticks: Option<u32>) -> Self {
+        Debouncer {
+            debounce_ticks,
+            long_press_ticks,
+            pressed: false,
+            count: 0,
+            held: 0,
+        }
+    }
+
+    /// Feeds the next raw sample into the state machine.
+    ///
+    /// # Arguments
+    ///
+    /// * active - Whether the input was active in this tick.
+    ///
+    /// # Return
+    ///
+    /// * The event caused by the sample, if any.
+    pub fn update(&mut self, active: bool) -> Option<Event> {
+        if active == self.pressed {
+            self.count = 0;
+        } else {
+            self.count = self.count.saturating_add(1);
+            if self.count >= self.debounce_ticks {
+                self.pressed = active;
+                self.count = 0;
+                self.held = 0;
+                return Some(if active {
+                    Event::Pressed
+                } else {
+                    Event::Released
+                });
+            }
+        }
+
+        if let (true, Some(long_press_ticks)) = (self.pressed, self.long_press_ticks) {
+            if self.held < long_press_ticks {
+                self.held += 1;
+                if self.held == long_press_ticks {
+                    return Some(Event::LongPress);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the debounced state of the input.
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+}
+
+/// A debounced input pin.
+pub struct Button<P: InputPin> {
+    pin: P,
+    active: Level,
+    debouncer: Debouncer,
+}
+
+impl<P: InputPin> Button<P> {
+    /// Creates a button.
+    ///
+    /// # Arguments
+    ///
+    /// * pin - The input pin, configured with a suitable pull resistor.
+    /// * active - The pin level while the button is pressed.
+    /// * debouncer - The debouncing configuration.
+    pub fn new(pin: P, active: Level, debouncer: Debouncer) -> Self {
+        Button {
+            pin,
+            active,
+            debouncer,
+        }
+    }
+
+    /// Samples the pin once and feeds the sample into the debouncer.
+    pub fn sample(&mut self) -> Result<Option<Event>, P::Error> {
+        let active = match self.active {
+            Level::Low => self.pin.try_is_low()?,
+            Level::High => self.pin.try_is_high()?,
+        };
+        Ok(self.debouncer.update(active))
+    }
+
+    /// Returns the debounced state of the button.
+    pub fn is_pressed(&self) -> bool {
+        self.debouncer.is_pressed()
+    }
+
+    /// Releases the pin.
+    pub fn free(self) -> P {
+        self.pin
+    }
+}
+
+/// Samples `buttons` if a period of `timer` has elapsed and calls `handler` for every event.
+///
+/// Meant to be called from the timer's interrupt handler or a main loop. The periodic timers
+/// set their match flag at the end of every period, and `try_wait` clears it, which also
+/// acknowledges the timer interrupt. Nothing is sampled if no period has elapsed since the
+/// last call or if the timer reports an error.
+///
+/// # Arguments
+///
+/// * timer - A running periodic timer, one period is one debouncer tick.
+/// * buttons - The buttons to sample.
+/// * handler - Called with the index of the button and the event.
+pub fn poll<T, P, F>(
+    timer: &mut T,
+    buttons: &mut [Button<P>],
+    mut handler: F,
+) -> Result<(), P::Error>
+where
+    T: CountDown + Periodic,
+    P: InputPin,
+    F: FnMut(usize, Event),
+{
+    if timer.try_wait().is_err() {
+        return Ok(());
+    }
+    for (index, button) in buttons.iter_mut().enumerate() {
+        if let Some(event) = button.sample()? {
+            handler(index, event);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `samples` into `debouncer` and returns the tick and event of every reported event.
+    fn feed(debouncer: &mut Debouncer, samples: &[bool], events: &mut [(usize, Event)]) -> usize {
+        let mut count = 0;
+        for (tick, active) in samples.iter().enumerate() {
+            if let Some(event) = debouncer.update(*active) {
+                events[count] = (tick, event);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn filters_bounces() {
+        let mut debouncer = Debouncer::new(3, None);
+        let mut events = [(0, Event::Pressed); 4];
+        let samples = [true, false, true, true, false, true, false, false];
+        assert_eq!(feed(&mut debouncer, &samples, &mut events), 0);
+        assert!(!debouncer.is_pressed());
+    }
+
+    #[test]
+    fn reports_press_and_release() {
+        let mut debouncer = Debouncer::new(3, None);
+        let mut events = [(0, Event::Pressed); 4];
+        let samples = [true, false, true, true, true, true, false, true, false, false, false];
+        assert_eq!(feed(&mut debouncer, &samples, &mut events), 2);
+        assert_eq!(events[..2], [(4, Event::Pressed), (10, Event::Released)]);
+        assert!(!debouncer.is_pressed());
+    }
+
+    #[test]
+    fn reports_long_press_once() {
+        let mut debouncer = Debouncer::new(2, Some(5));
+        let mut events = [(0, Event::Pressed); 4];
+        let mut samples = [true; 20];
+        samples[18] = false;
+        samples[19] = false;
+        assert_eq!(feed(&mut debouncer, &samples, &mut events), 3);
+        assert_eq!(
+            events[..3],
+            [(1, Event::Pressed), (6, Event::LongPress), (19, Event::Released)]
+        );
+
+        // A short press does not reach the long press time
+        let samples = [true, true, true, false, false];
+        assert_eq!(feed(&mut debouncer, &samples, &mut events), 2);
+        assert_eq!(events[..2], [(1, Event::Pressed), (4, Event::Released)]);
+    }
+
+    #[test]
+    fn zero_debounce_ticks_reports_every_change() {
+        let mut debouncer = Debouncer::new(0, None);
+        let mut events = [(0, Event::Pressed); 4];
+        let samples = [false, true, true, false];
+        assert_eq!(feed(&mut debouncer, &samples, &mut events), 2);
+        assert_eq!(events[..2], [(1, Event::Pressed), (3, Event::Released)]);
+    }
+}
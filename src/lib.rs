@@ -7,10 +7,20 @@ pub use embedded_hal::timer::*;
 pub use lpc178x_7x as pac;
 pub use nb;
 
+#[cfg(any(
+    all(feature = "lqfp144", any(feature = "lqfp208", feature = "tfbga180", feature = "tfbga208")),
+    all(feature = "lqfp208", any(feature = "tfbga180", feature = "tfbga208")),
+    all(feature = "tfbga180", feature = "tfbga208"),
+))]
+compile_error!("Only one of the package features lqfp144, lqfp208, tfbga180 and tfbga208 may be selected");
+
+mod ahbsram;
+pub mod bitbang;
 pub mod clock;
+pub mod exti;
 pub mod gpio;
 pub mod timer;
-mod typestates;
+pub mod typestates;
 pub mod uart;
 
 pub struct Hal {
@@ -20,6 +30,7 @@ pub struct Hal {
     pub gpio2: gpio::gpio2::GPIO2,
     pub gpio3: gpio::gpio3::GPIO3,
     pub gpio4: gpio::gpio4::GPIO4,
+    #[cfg(not(feature = "lqfp144"))]
     pub gpio5: gpio::gpio5::GPIO5,
     pub uart0: uart::Uart0<Disabled>,
     pub uart1: uart::Uart1<Disabled>,
@@ -30,11 +41,44 @@ pub struct Hal {
     pub timer1: timer::Timer1<Disabled, NonPeriodic>,
     pub timer2: timer::Timer2<Disabled, NonPeriodic>,
     pub timer3: timer::Timer3<Disabled, NonPeriodic>,
+    // Peripherals without a driver yet, only present if the selected chip variant has them
+    #[cfg(eeprom)]
+    pub eeprom: pac::EEPROM,
+    #[cfg(ethernet)]
+    pub ethernet: pac::ETHERNET,
+    #[cfg(lcd)]
+    pub lcd: pac::LCD,
+    #[cfg(usb)]
+    pub usb: pac::USB,
 }
 
 impl Hal {
+    /// Creates the HAL, taking ownership of the PAC peripherals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the PAC peripherals have already been taken, see `Hal::take`.
     pub fn new() -> Hal {
-        let _peripherals = pac::Peripherals::take().unwrap();
+        Hal::take().unwrap()
+    }
+
+    /// Creates the HAL, unless the PAC peripherals have already been taken.
+    ///
+    /// # Return
+    ///
+    /// `Some(Hal)` on the first call, `None` afterwards.
+    pub fn take() -> Option<Hal> {
+        pac::Peripherals::take().map(Hal::from_peripherals)
+    }
+
+    /// Creates the HAL from the PAC peripherals, e.g. those passed to RTIC's `init`.
+    ///
+    /// # Example
+    /// ```
+    /// let peripherals = pac::Peripherals::take().unwrap();
+    /// let hal = Hal::from_peripherals(peripherals);
+    /// ```
+    pub fn from_peripherals(peripherals: pac::Peripherals) -> Hal {
         Hal {
             clock: clock::Clock::<Disabled>::new(),
             gpio0: gpio::gpio0::GPIO0::new(),
@@ -42,16 +86,25 @@ impl Hal {
             gpio2: gpio::gpio2::GPIO2::new(),
             gpio3: gpio::gpio3::GPIO3::new(),
             gpio4: gpio::gpio4::GPIO4::new(),
+            #[cfg(not(feature = "lqfp144"))]
             gpio5: gpio::gpio5::GPIO5::new(),
-            uart0: _peripherals.UART0.into(),
-            uart1: _peripherals.UART1.into(),
-            uart2: _peripherals.UART2.into(),
-            uart3: _peripherals.UART3.into(),
-            uart4: _peripherals.UART4.into(),
-            timer0: _peripherals.TIMER0.into(),
-            timer1: _peripherals.TIMER1.into(),
-            timer2: _peripherals.TIMER2.into(),
-            timer3: _peripherals.TIMER3.into(),
+            uart0: peripherals.UART0.into(),
+            uart1: peripherals.UART1.into(),
+            uart2: peripherals.UART2.into(),
+            uart3: peripherals.UART3.into(),
+            uart4: peripherals.UART4.into(),
+            timer0: peripherals.TIMER0.into(),
+            timer1: peripherals.TIMER1.into(),
+            timer2: peripherals.TIMER2.into(),
+            timer3: peripherals.TIMER3.into(),
+            #[cfg(eeprom)]
+            eeprom: peripherals.EEPROM,
+            #[cfg(ethernet)]
+            ethernet: peripherals.ETHERNET,
+            #[cfg(lcd)]
+            lcd: peripherals.LCD,
+            #[cfg(usb)]
+            usb: peripherals.USB,
         }
     }
 }
@@ -1,6 +1,9 @@
 use crate::clock::Clock;
+use crate::gpio::{self, function, Edge, Level};
 use crate::pac::{TIMER0, TIMER1, TIMER2, TIMER3};
-use crate::typestates::{Disabled, Enabled, InitState, NonPeriodic, Periodic, TimerType};
+use crate::typestates::{
+    AltFunction, Disabled, Enabled, InitState, NonPeriodic, Periodic, TimerType,
+};
 
 use core::marker::PhantomData;
 
@@ -8,10 +11,107 @@ use embedded_hal::timer::{Cancel, CountDown, Periodic as HalPeriodic};
 use embedded_time::duration::*;
 use nb::Error::WouldBlock;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TimerError {
     NotStarted,
 }
 
+/// Implemented by pins connected to a capture input of `TIMER`.
+pub trait CapturePin<TIMER> {
+    /// Index of the capture channel, n in CAPx.n.
+    const CHANNEL: u8;
+}
+
+/// A timer capturing its counter value on edges of its capture pins.
+///
+/// The timer runs freely with its 1 MHz tick, so timestamps wrap around after about 71 minutes.
+/// Differences of timestamps are calculated with wrapping arithmetic, see `Pulse`. The capture
+/// pins are used through a `CaptureChannel` each, all channels share the timestamps.
+pub struct InputCapture<TIMER> {
+    timer: TIMER,
+}
+
+/// A capture channel of an `InputCapture`, owning its capture pin.
+///
+/// With `set_interrupt` enabled, every capture sets the channel's flag in the timer's interrupt
+/// register, which also requests the timer interrupt. The interrupt reaches the CPU once it is
+/// unmasked in the NVIC. Without it the flag is not raised, and `try_capture` detects captures
+/// by a change of the capture register instead. A capture of the same counter value as the
+/// previous one, a full counter period of about 71 minutes later, then goes unnoticed.
+pub struct CaptureChannel<'a, TIMER, PIN> {
+    timer: &'a TIMER,
+    pin: PIN,
+    last: u32,
+    start: Option<u32>,
+    end: Option<u32>,
+}
+
+/// Timestamps of a pulse and the start of the next one, e.g. rising, falling and rising edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pulse {
+    pub start: u32,
+    pub end: u32,
+    pub next_start: u32,
+}
+
+impl Pulse {
+    /// Returns the duration of the pulse.
+    pub fn width(&self) -> Microseconds<u32> {
+        Microseconds(self.end.wrapping_sub(self.start))
+    }
+
+    /// Returns the time from the start of the pulse to the start of the next one.
+    pub fn period(&self) -> Microseconds<u32> {
+        Microseconds(self.next_start.wrapping_sub(self.start))
+    }
+
+    /// Returns the share of the period taken up by the pulse, in per mille.
+    pub fn duty_cycle(&self) -> u16 {
+        let period = *self.period().integer() as u64;
+        if period == 0 {
+            return 0;
+        }
+        (*self.width().integer() as u64 * 1000 / period) as u16
+    }
+}
+
+/// Action on the match output pin when the counter reaches the match value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchAction {
+    Nothing,
+    Clear,
+    Set,
+    Toggle,
+}
+
+/// Implemented by pins connected to a match output of `TIMER`.
+pub trait MatchPin<TIMER> {
+    /// Index of the match channel, n in MATx.n.
+    const CHANNEL: u8;
+}
+
+/// A timer driving its match output pins without CPU involvement.
+///
+/// The counter runs with the timer's 1 MHz tick. Whenever it reaches the match value of a
+/// channel, the configured `MatchAction` is applied to the channel's pin. The match output pins
+/// are used through a `MatchChannel` each, all channels share the counter.
+pub struct MatchOutput<TIMER> {
+    timer: TIMER,
+}
+
+/// A match channel of a `MatchOutput`, owning its match output pin.
+pub struct MatchChannel<'a, TIMER, PIN> {
+    timer: &'a TIMER,
+    pin: PIN,
+}
+
+/// A timer counting edges on a capture pin instead of clock ticks.
+pub struct Counter<TIMER, PIN> {
+    timer: TIMER,
+    pin: PIN,
+    prescaler: u32,
+}
+
 macro_rules! timers {
     ($(($type: ident, $hardware: ident, $pcon: ident)),* $(,)?) => {
         $(
@@ -68,7 +168,8 @@ macro_rules! timers {
 
                 fn try_wait(&mut self) -> nb::Result<(), Self::Error> {
                     if self.timer.ir.read().mr0int().bit_is_set() {
-                        self.timer.ir.write(|w| w.mr0int().clear_bit());
+                        // Interrupt flags are cleared by writing 1
+                        self.timer.ir.write(|w| w.mr0int().set_bit());
                         Ok(())
                     } else {
                         Err(WouldBlock)
@@ -90,6 +191,432 @@ macro_rules! timers {
             }
 
             impl HalPeriodic for $type<Enabled, Periodic> { }
+
+            impl<T> $type<Enabled, T>
+                where T: TimerType
+            {
+                /// Turns the timer into a free running counter for captures on its capture pins.
+                ///
+                /// # Example
+                /// ```
+                /// let pins = hal.gpio1.split();
+                /// let capture = hal.timer0.enable(&clock).into_input_capture();
+                /// let mut cap0 = capture.channel(pins.p1_26.into_function::<gpio::function::T0Cap0>(), Edge::Rising);
+                /// let mut cap1 = capture.channel(pins.p1_27.into_function::<gpio::function::T0Cap1>(), Edge::Falling);
+                /// let start = block!(cap0.try_capture()).unwrap();
+                /// let end = block!(cap1.try_capture()).unwrap();
+                /// ```
+                pub fn into_input_capture(self) -> InputCapture<$type<Enabled, NonPeriodic>> {
+                    self.timer.tcr.write(|w| w.crst().set_bit());
+                    self.timer.mcr.write(|w| unsafe { w.bits(0) });
+                    self.timer.ccr.write(|w| unsafe { w.bits(0) });
+                    self.timer.ir.write(|w| unsafe { w.bits(0b11 << 4) });
+                    self.timer.tcr.write(|w| w.cen().set_bit());
+                    InputCapture {
+                        timer: $type::<Enabled, NonPeriodic> { state: PhantomData, typ: PhantomData, timer: self.timer },
+                    }
+                }
+            }
+
+            impl InputCapture<$type<Enabled, NonPeriodic>> {
+                /// Starts capturing on `pin`.
+                ///
+                /// # Arguments
+                ///
+                /// * pin - A pin connected to a capture input of the timer.
+                /// * edge - The edges captured.
+                ///
+                /// The capture interrupt starts out disabled, see `CaptureChannel::set_interrupt`.
+                pub fn channel<P>(&self, pin: P, edge: Edge) -> CaptureChannel<'_, $type<Enabled, NonPeriodic>, P>
+                    where P: CapturePin<$type<Enabled, NonPeriodic>>
+                {
+                    let mut channel = CaptureChannel { timer: &self.timer, pin, last: 0, start: None, end: None };
+                    channel.set_edge(edge);
+                    channel.discard();
+                    channel
+                }
+
+                /// Returns the current counter value, on the same time base as the captures.
+                pub fn now(&self) -> u32 {
+                    self.timer.timer.tc.read().bits()
+                }
+
+                /// Stops capturing and returns the timer, configured for `CountDown` again.
+                ///
+                /// The pins are returned by `CaptureChannel::free`.
+                pub fn release(self) -> $type<Enabled, NonPeriodic> {
+                    let timer = self.timer.timer;
+                    timer.tcr.write(|w| w.crst().set_bit());
+                    timer.ccr.write(|w| unsafe { w.bits(0) });
+                    timer.ir.write(|w| unsafe { w.bits(0b11 << 4) });
+                    timer.mcr.write(|w| w.mr0i().set_bit().mr0s().set_bit());
+                    $type::<Enabled, NonPeriodic> { state: PhantomData, typ: PhantomData, timer }
+                }
+            }
+
+            impl<'a, P> CaptureChannel<'a, $type<Enabled, NonPeriodic>, P>
+                where P: CapturePin<$type<Enabled, NonPeriodic>>
+            {
+                /// Selects the edges captured.
+                pub fn set_edge(&mut self, edge: Edge) {
+                    let bits = Self::edge_bits(edge) << (3 * P::CHANNEL);
+                    let mask = 0b011 << (3 * P::CHANNEL);
+                    self.timer.timer.ccr.modify(|r, w| unsafe { w.bits((r.bits() & !mask) | bits) });
+                }
+
+                /// Enables or disables the timer interrupt on a capture.
+                pub fn set_interrupt(&mut self, enabled: bool) {
+                    let bit = 0b100 << (3 * P::CHANNEL);
+                    self.timer.timer.ccr.modify(|r, w| unsafe {
+                        w.bits(if enabled { r.bits() | bit } else { r.bits() & !bit })
+                    });
+                    self.discard();
+                }
+
+                /// Returns the timestamp of the last capture and clears the capture flag.
+                ///
+                /// Returns `WouldBlock` if nothing was captured since the last call.
+                pub fn try_capture(&mut self) -> nb::Result<u32, TimerError> {
+                    let captured = if self.interrupt_enabled() {
+                        self.timer.timer.ir.read().bits() & (1 << (4 + P::CHANNEL)) != 0
+                    } else {
+                        self.timer.timer.cr[P::CHANNEL as usize].read().bits() != self.last
+                    };
+                    if !captured {
+                        return Err(WouldBlock);
+                    }
+                    self.discard();
+                    Ok(self.last)
+                }
+
+                /// Measures a pulse and the start of the next one.
+                ///
+                /// Takes over the edge selection of the channel and captures the start, the end and
+                /// the next start of a pulse, switching the captured edge after every capture. The
+                /// next start is the start of the following measurement, so calling it in a loop
+                /// measures every period. Pulses and pauses must be longer than the time between
+                /// two calls. Returns `WouldBlock` until the measurement is complete.
+                ///
+                /// # Arguments
+                ///
+                /// * active - The level of the pin during the pulse.
+                ///
+                /// # Example
+                /// ```
+                /// let pulse = block!(cap0.try_pulse(Level::High)).unwrap();
+                /// let duty_cycle = pulse.duty_cycle();
+                /// ```
+                pub fn try_pulse(&mut self, active: Level) -> nb::Result<Pulse, TimerError> {
+                    let (start_edge, end_edge) = match active {
+                        Level::High => (Edge::Rising, Edge::Falling),
+                        Level::Low => (Edge::Falling, Edge::Rising),
+                    };
+                    match (self.start, self.end) {
+                        (None, _) => {
+                            let selected = (self.timer.timer.ccr.read().bits() >> (3 * P::CHANNEL)) & 0b11;
+                            if selected != Self::edge_bits(start_edge) {
+                                // Discard captures of other edges made before the measurement
+                                self.set_edge(start_edge);
+                                self.discard();
+                                return Err(WouldBlock);
+                            }
+                            let start = self.try_capture()?;
+                            self.set_edge(end_edge);
+                            self.start = Some(start);
+                            Err(WouldBlock)
+                        }
+                        (Some(_), None) => {
+                            let end = self.try_capture()?;
+                            self.set_edge(start_edge);
+                            self.end = Some(end);
+                            Err(WouldBlock)
+                        }
+                        (Some(start), Some(end)) => {
+                            let next_start = self.try_capture()?;
+                            self.set_edge(end_edge);
+                            self.start = Some(next_start);
+                            self.end = None;
+                            Ok(Pulse { start, end, next_start })
+                        }
+                    }
+                }
+
+                /// Stops capturing and returns the pin.
+                pub fn free(self) -> P {
+                    self.timer.timer.ccr.modify(|r, w| unsafe { w.bits(r.bits() & !(0b111 << (3 * P::CHANNEL))) });
+                    self.clear_flag();
+                    self.pin
+                }
+
+                fn edge_bits(edge: Edge) -> u32 {
+                    match edge {
+                        Edge::Rising => 0b01,
+                        Edge::Falling => 0b10,
+                        Edge::RisingFalling => 0b11,
+                    }
+                }
+
+                fn interrupt_enabled(&self) -> bool {
+                    self.timer.timer.ccr.read().bits() & (0b100 << (3 * P::CHANNEL)) != 0
+                }
+
+                fn clear_flag(&self) {
+                    // Interrupt flags are cleared by writing 1
+                    self.timer.timer.ir.write(|w| unsafe { w.bits(1 << (4 + P::CHANNEL)) });
+                }
+
+                /// Marks the current capture as seen, so `try_capture` waits for the next one.
+                fn discard(&mut self) {
+                    self.clear_flag();
+                    self.last = self.timer.timer.cr[P::CHANNEL as usize].read().bits();
+                }
+            }
+
+            impl<T> $type<Enabled, T>
+                where T: TimerType
+            {
+                /// Stops the timer and configures it to drive its match output pins.
+                ///
+                /// The counter is started with `MatchOutput::start` once the channels are set up.
+                ///
+                /// # Example
+                /// ```
+                /// let pins = hal.gpio0.split();
+                /// let output = hal.timer2.enable(&clock).into_match_output();
+                /// let mut clock_out = output.channel(pins.p0_6.into_function::<gpio::function::T2Mat0>(), MatchAction::Toggle);
+                /// let mut strobe = output.channel(pins.p0_7.into_function::<gpio::function::T2Mat1>(), MatchAction::Set);
+                /// clock_out.set_match(500.microseconds());
+                /// clock_out.set_reset_on_match(true);
+                /// strobe.set_match(100.microseconds());
+                /// output.start();
+                /// ```
+                pub fn into_match_output(self) -> MatchOutput<$type<Enabled, NonPeriodic>> {
+                    self.timer.tcr.write(|w| w.crst().set_bit());
+                    self.timer.mcr.write(|w| unsafe { w.bits(0) });
+                    self.timer.emr.write(|w| unsafe { w.bits(0) });
+                    MatchOutput {
+                        timer: $type::<Enabled, NonPeriodic> { state: PhantomData, typ: PhantomData, timer: self.timer },
+                    }
+                }
+            }
+
+            impl MatchOutput<$type<Enabled, NonPeriodic>> {
+                /// Starts driving `pin`.
+                ///
+                /// # Arguments
+                ///
+                /// * pin - A pin connected to a match output of the timer.
+                /// * action - The action on the pin on a match.
+                pub fn channel<P>(&self, pin: P, action: MatchAction) -> MatchChannel<'_, $type<Enabled, NonPeriodic>, P>
+                    where P: MatchPin<$type<Enabled, NonPeriodic>>
+                {
+                    let mut channel = MatchChannel { timer: &self.timer, pin };
+                    channel.set_action(action);
+                    channel
+                }
+
+                /// Starts the counter.
+                pub fn start(&self) {
+                    self.timer.timer.tcr.write(|w| w.cen().set_bit());
+                }
+
+                /// Stops the counter and resets it to zero.
+                pub fn stop(&self) {
+                    self.timer.timer.tcr.write(|w| w.crst().set_bit());
+                }
+
+                /// Stops driving the pins and returns the timer, configured for `CountDown` again.
+                ///
+                /// The pins are returned by `MatchChannel::free`.
+                pub fn release(self) -> $type<Enabled, NonPeriodic> {
+                    self.stop();
+                    let timer = self.timer.timer;
+                    timer.emr.write(|w| unsafe { w.bits(0) });
+                    timer.ir.write(|w| unsafe { w.bits(0b1111) });
+                    timer.mcr.write(|w| w.mr0i().set_bit().mr0s().set_bit());
+                    $type::<Enabled, NonPeriodic> { state: PhantomData, typ: PhantomData, timer }
+                }
+            }
+
+            impl<'a, P> MatchChannel<'a, $type<Enabled, NonPeriodic>, P>
+                where P: MatchPin<$type<Enabled, NonPeriodic>>
+            {
+                fn modify_mcr(&mut self, bit: u32, enabled: bool) {
+                    let bit = bit << (3 * P::CHANNEL);
+                    self.timer.timer.mcr.modify(|r, w| unsafe {
+                        w.bits(if enabled { r.bits() | bit } else { r.bits() & !bit })
+                    });
+                }
+
+                /// Selects the action on the pin on a match.
+                pub fn set_action(&mut self, action: MatchAction) {
+                    let bits = match action {
+                        MatchAction::Nothing => 0b00,
+                        MatchAction::Clear => 0b01,
+                        MatchAction::Set => 0b10,
+                        MatchAction::Toggle => 0b11,
+                    } << (4 + 2 * P::CHANNEL);
+                    let mask = 0b11 << (4 + 2 * P::CHANNEL);
+                    self.timer.timer.emr.modify(|r, w| unsafe { w.bits((r.bits() & !mask) | bits) });
+                }
+
+                /// Sets the counter value at which the match occurs.
+                pub fn set_match<T>(&mut self, time: T)
+                    where T: Into<Microseconds<u32>>
+                {
+                    let ticks = *time.into().integer();
+                    self.timer.timer.mr[P::CHANNEL as usize].write(|w| unsafe { w.bits(ticks) });
+                }
+
+                /// Enables or disables resetting the counter on a match, for periodic outputs.
+                ///
+                /// The counter is shared, so the reset applies to all channels of the timer.
+                pub fn set_reset_on_match(&mut self, enabled: bool) {
+                    self.modify_mcr(0b010, enabled);
+                }
+
+                /// Enables or disables stopping the counter on a match, for single pulses.
+                ///
+                /// The counter is shared, so stopping applies to all channels of the timer.
+                pub fn set_stop_on_match(&mut self, enabled: bool) {
+                    self.modify_mcr(0b100, enabled);
+                }
+
+                /// Enables or disables the timer interrupt on a match.
+                pub fn set_interrupt(&mut self, enabled: bool) {
+                    self.modify_mcr(0b001, enabled);
+                }
+
+                /// Drives `level` on the pin immediately, e.g. as starting level before `start`.
+                pub fn set_output(&mut self, level: Level) {
+                    let bit = 1 << P::CHANNEL;
+                    self.timer.timer.emr.modify(|r, w| unsafe {
+                        w.bits(match level {
+                            Level::Low => r.bits() & !bit,
+                            Level::High => r.bits() | bit,
+                        })
+                    });
+                }
+
+                /// Returns the level currently driven on the pin.
+                pub fn output(&self) -> Level {
+                    if self.timer.timer.emr.read().bits() & (1 << P::CHANNEL) != 0 {
+                        Level::High
+                    } else {
+                        Level::Low
+                    }
+                }
+
+                /// Stops driving the pin and returns it.
+                pub fn free(mut self) -> P {
+                    self.set_action(MatchAction::Nothing);
+                    self.set_reset_on_match(false);
+                    self.set_stop_on_match(false);
+                    self.set_interrupt(false);
+                    self.pin
+                }
+            }
+
+            impl<T> $type<Enabled, T>
+                where T: TimerType
+            {
+                /// Turns the timer into a counter of edges on `pin`, e.g. from a flow meter.
+                ///
+                /// # Arguments
+                ///
+                /// * pin - A pin connected to a capture input of the timer.
+                /// * edge - The edges counted.
+                ///
+                /// # Example
+                /// ```
+                /// let pins = hal.gpio1.split();
+                /// let pin = pins.p1_18.into_function::<gpio::function::T1Cap0>();
+                /// let mut counter = hal.timer1.enable(&clock).into_counter(pin, Edge::Rising);
+                /// counter.set_threshold(Some(1000));
+                /// while !counter.is_threshold_reached() {}
+                /// ```
+                pub fn into_counter<P>(self, pin: P, edge: Edge) -> Counter<$type<Enabled, NonPeriodic>, P>
+                    where P: CapturePin<$type<Enabled, NonPeriodic>>
+                {
+                    let mode = match edge {
+                        Edge::Rising => 0b01,
+                        Edge::Falling => 0b10,
+                        Edge::RisingFalling => 0b11,
+                    };
+                    self.timer.tcr.write(|w| w.crst().set_bit());
+                    self.timer.mcr.write(|w| unsafe { w.bits(0) });
+                    // The counted input must not be used for captures
+                    self.timer.ccr.modify(|r, w| unsafe { w.bits(r.bits() & !(0b111 << (3 * P::CHANNEL))) });
+                    self.timer.ctcr.write(|w| unsafe { w.bits(mode | ((P::CHANNEL as u32) << 2)) });
+                    // Every edge increments the counter
+                    let prescaler = self.timer.pr.read().bits();
+                    self.timer.pr.write(|w| unsafe { w.bits(0) });
+                    self.timer.ir.write(|w| unsafe { w.bits(0b1) });
+                    self.timer.tcr.write(|w| w.cen().set_bit());
+                    Counter {
+                        timer: $type::<Enabled, NonPeriodic> { state: PhantomData, typ: PhantomData, timer: self.timer },
+                        pin,
+                        prescaler,
+                    }
+                }
+            }
+
+            impl<P> Counter<$type<Enabled, NonPeriodic>, P>
+                where P: CapturePin<$type<Enabled, NonPeriodic>>
+            {
+                /// Returns the number of edges counted.
+                pub fn count(&self) -> u32 {
+                    self.timer.timer.tc.read().bits()
+                }
+
+                /// Resets the count to zero.
+                pub fn reset(&mut self) {
+                    self.timer.timer.tcr.write(|w| w.crst().set_bit());
+                    self.timer.timer.tcr.write(|w| w.cen().set_bit());
+                }
+
+                /// Sets the count at which the threshold flag is raised, `None` disables the threshold.
+                ///
+                /// The flag also requests the timer interrupt, which reaches the CPU once it is
+                /// unmasked in the NVIC.
+                ///
+                /// # Arguments
+                ///
+                /// * threshold - The count raising the flag.
+                pub fn set_threshold(&mut self, threshold: Option<u32>) {
+                    match threshold {
+                        Some(threshold) => {
+                            self.timer.timer.mr[0].write(|w| unsafe { w.bits(threshold) });
+                            // The match flag is only raised with its interrupt bit set
+                            self.timer.timer.mcr.write(|w| w.mr0i().set_bit());
+                        }
+                        None => self.timer.timer.mcr.write(|w| unsafe { w.bits(0) }),
+                    }
+                    self.clear_threshold_flag();
+                }
+
+                /// Returns whether the count reached the threshold since the flag was last cleared.
+                pub fn is_threshold_reached(&self) -> bool {
+                    self.timer.timer.ir.read().bits() & 0b1 != 0
+                }
+
+                /// Clears the threshold flag.
+                pub fn clear_threshold_flag(&mut self) {
+                    self.timer.timer.ir.write(|w| unsafe { w.bits(0b1) });
+                }
+
+                /// Stops counting and returns the timer, configured for `CountDown` again, and the pin.
+                pub fn release(self) -> ($type<Enabled, NonPeriodic>, P) {
+                    let prescaler = self.prescaler;
+                    let timer = self.timer.timer;
+                    timer.tcr.write(|w| w.crst().set_bit());
+                    timer.ctcr.write(|w| unsafe { w.bits(0) });
+                    timer.pr.write(|w| unsafe { w.bits(prescaler) });
+                    timer.ir.write(|w| unsafe { w.bits(0b1) });
+                    timer.mcr.write(|w| w.mr0i().set_bit().mr0s().set_bit());
+                    ($type::<Enabled, NonPeriodic> { state: PhantomData, typ: PhantomData, timer }, self.pin)
+                }
+            }
         )*
     }
 }
@@ -99,4 +626,52 @@ timers!(
     (Timer1, TIMER1, pctim1),
     (Timer2, TIMER2, pctim2),
     (Timer3, TIMER3, pctim3),
-);
\ No newline at end of file
+);
+
+macro_rules! capture_pins {
+    ($(($type: ident, $pin: ident, $io: ident, $function: ident, $func: ident, $channel: expr)),* $(,)?) => {
+        $(
+            gpio::pin_functions!(($pin, $io, $function, $func));
+
+            impl CapturePin<$type<Enabled, NonPeriodic>> for gpio::$pin<AltFunction<function::$function>> {
+                const CHANNEL: u8 = $channel;
+            }
+        )*
+    }
+}
+
+capture_pins!(
+    (Timer0, P1_26, p1_26, T0Cap0, t0_cap0, 0),
+    (Timer0, P1_27, p1_27, T0Cap1, t0_cap1, 1),
+    (Timer1, P1_18, p1_18, T1Cap0, t1_cap0, 0),
+    (Timer1, P1_19, p1_19, T1Cap1, t1_cap1, 1),
+    (Timer2, P0_4, p0_4, T2Cap0, t2_cap0, 0),
+    (Timer2, P0_5, p0_5, T2Cap1, t2_cap1, 1),
+    (Timer3, P0_23, p0_23, T3Cap0, t3_cap0, 0),
+    (Timer3, P0_24, p0_24, T3Cap1, t3_cap1, 1),
+);
+
+macro_rules! match_pins {
+    ($(($type: ident, $pin: ident, $io: ident, $function: ident, $func: ident, $channel: expr)),* $(,)?) => {
+        $(
+            gpio::pin_functions!(($pin, $io, $function, $func));
+
+            impl MatchPin<$type<Enabled, NonPeriodic>> for gpio::$pin<AltFunction<function::$function>> {
+                const CHANNEL: u8 = $channel;
+            }
+        )*
+    }
+}
+
+match_pins!(
+    (Timer0, P1_28, p1_28, T0Mat0, t0_mat0, 0),
+    (Timer0, P1_29, p1_29, T0Mat1, t0_mat1, 1),
+    (Timer1, P1_22, p1_22, T1Mat0, t1_mat0, 0),
+    (Timer1, P1_25, p1_25, T1Mat1, t1_mat1, 1),
+    (Timer2, P0_6, p0_6, T2Mat0, t2_mat0, 0),
+    (Timer2, P0_7, p0_7, T2Mat1, t2_mat1, 1),
+    (Timer2, P0_8, p0_8, T2Mat2, t2_mat2, 2),
+    (Timer2, P0_9, p0_9, T2Mat3, t2_mat3, 3),
+    (Timer3, P0_10, p0_10, T3Mat0, t3_mat0, 0),
+    (Timer3, P0_11, p0_11, T3Mat1, t3_mat1, 1),
+);
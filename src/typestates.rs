@@ -1,5 +1,6 @@
 use core::marker::PhantomData;
 use gpio::direction::Direction;
+use gpio::function::Function;
 
 mod sealed {
     pub trait Sealed {}
@@ -23,6 +24,16 @@ pub struct Gpio<D: Direction> {
 impl<D> sealed::Sealed for Gpio<D> where D: Direction {}
 impl<D> PinState for Gpio<D> where D: Direction {}
 
+pub struct AltFunction<F: Function> {
+    _f: PhantomData<F>,
+}
+impl<F> sealed::Sealed for AltFunction<F> where F: Function {}
+impl<F> PinState for AltFunction<F> where F: Function {}
+
+pub struct Analog;
+impl sealed::Sealed for Analog {}
+impl PinState for Analog {}
+
 pub trait TimerType: sealed::Sealed {}
 
 pub struct Periodic;
@@ -48,5 +59,42 @@ pub mod gpio {
         pub struct Output;
         impl crate::typestates::sealed::Sealed for Output {}
         impl Direction for Output {}
+
+        pub struct OpenDrain;
+        impl crate::typestates::sealed::Sealed for OpenDrain {}
+        impl Direction for OpenDrain {}
+
+        pub struct Dynamic;
+        impl crate::typestates::sealed::Sealed for Dynamic {}
+        impl Direction for Dynamic {}
+    }
+
+    pub mod function {
+        pub trait Function: crate::typestates::sealed::Sealed {}
+
+        macro_rules! functions {
+            ($($function: ident),* $(,)?) => {
+                $(
+                    pub struct $function;
+                    impl crate::typestates::sealed::Sealed for $function {}
+                    impl Function for $function {}
+                )*
+            }
+        }
+
+        // UART
+        functions!(U0Rxd, U0Txd, U1Rxd, U1Txd, U2Rxd, U2Txd, U3Rxd, U3Txd, U4Rxd, U4Txd);
+
+        // External interrupts
+        functions!(Eint0, Eint1, Eint2, Eint3);
+
+        // DAC
+        functions!(DacOut);
+
+        // Timer capture inputs
+        functions!(T0Cap0, T0Cap1, T1Cap0, T1Cap1, T2Cap0, T2Cap1, T3Cap0, T3Cap1);
+
+        // Timer match outputs
+        functions!(T0Mat0, T0Mat1, T1Mat0, T1Mat1, T2Mat0, T2Mat1, T2Mat2, T2Mat3, T3Mat0, T3Mat1);
     }
 }
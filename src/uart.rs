@@ -1,19 +1,41 @@
-use crate::gpio;
-use crate::typestates::{Disabled, Enabled, InitState, PinState};
+use crate::gpio::{self, function};
+use crate::typestates::{AltFunction, Disabled, Enabled, InitState};
 use core::convert::From;
 use core::fmt;
 use core::marker::PhantomData;
 
+use embedded_hal::blocking::serial::{write, Write as BlockingWrite};
 use embedded_hal::serial::{Read, Write};
+use embedded_hal::timer::CountDown;
 
 use crate::pac::{UART0, UART1, UART2, UART3, UART4};
 use nb::Error::WouldBlock;
 
-pub trait UartRx<UART> {
-    fn into_uartrx(&self);
-}
-pub trait UartTx<UART> {
-    fn into_uarttx(&self);
+#[cfg(feature = "framing")]
+pub mod framing;
+#[cfg(feature = "modbus")]
+pub mod modbus;
+
+/// Implemented by pins connected to the Rx function of `UART`.
+pub trait UartRx<UART> {}
+/// Implemented by pins connected to the Tx function of `UART`.
+pub trait UartTx<UART> {}
+
+/// Errors reported by the UART HAL wrappers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// A received character was lost because the receive FIFO was full.
+    Overrun,
+    /// A character was received with a wrong parity bit.
+    Parity,
+    /// A character was received without a valid stop bit.
+    Framing,
+    /// The Rx line was held low for longer than a full character.
+    Break,
+    /// A blocking operation did not complete before its timer expired.
+    Timeout,
+    /// Starting or waiting for the timer of a blocking operation failed.
+    Timer,
 }
 
 pub struct UartConfig {
@@ -59,8 +81,8 @@ macro_rules! uarts {
                 ///
                 /// # Arguments
                 ///
-                /// * rx - A pin implementing the matching UartXRx trait, that can be used for Rx.
-                /// * tx - A pin implementing the matching UartXTx trait, that can be used for Tx.
+                /// * rx - A pin connected to the matching UxRxd function, implementing UartRx.
+                /// * tx - A pin connected to the matching UxTxd function, implementing UartTx.
                 /// * baudrate - The baudrate for the UART.
                 /// * clock - Enabled CPU clock component.
                 ///
@@ -69,9 +91,9 @@ macro_rules! uarts {
                 /// let hal = crate::Hal::new();
                 /// let clock = hal.clock.enable(120_000_000, 12_000_000);
                 /// let pins = hal.gpio0.split();
-                /// let tx = pins.p0_2;
-                /// let rx = pins.p0_3;
-                /// let uart = hal.uart0.enable(rx, tx);
+                /// let tx = pins.p0_2.into_function::<gpio::function::U0Txd>();
+                /// let rx = pins.p0_3.into_function::<gpio::function::U0Rxd>();
+                /// let uart = hal.uart0.enable(clock.get_uart_config(115200), rx, tx);
                 /// ```
                 pub fn enable<Rx, Tx>(self, config: UartConfig, rx: Rx, tx: Tx) -> $type<Enabled, Rx, Tx>
                 where
@@ -80,9 +102,6 @@ macro_rules! uarts {
                 {
                     unsafe { (*crate::pac::SYSCON::ptr()).pconp.write(|w| w.$pcon().set_bit()); };
 
-                    rx.into_uartrx();
-                    tx.into_uarttx();
-
                     self._uart.fcr().write(|w| w.fifoen().set_bit());
                     self._uart.lcr.write(|w| w.wls()._8_bit_character_leng().dlab().set_bit());
                     unsafe {
@@ -97,15 +116,52 @@ macro_rules! uarts {
 
             }
 
+            impl<Rx, Tx> $type<Enabled, Rx, Tx>
+            where
+                Rx: UartRx<$type<Enabled, Rx, Tx>>,
+                Tx: UartTx<$type<Enabled, Rx, Tx>>,
+            {
+                /// Powers down the UART hardware and releases its pins.
+                ///
+                /// # Return
+                ///
+                /// * The disabled UART, the Rx pin and the Tx pin. The pins keep their UART function
+                ///   until they are converted, e.g. with `into_reset_state`.
+                ///
+                /// # Example
+                /// ```
+                /// let (uart, rx, tx) = uart.release();
+                /// let rx = rx.into_reset_state();
+                /// let tx = tx.into_reset_state();
+                /// ```
+                pub fn release(self) -> ($type<Disabled>, Rx, Tx) {
+                    unsafe { (*crate::pac::SYSCON::ptr()).pconp.modify(|_, w| w.$pcon().clear_bit()); };
+                    ($type { _state: PhantomData, _uart: self._uart, _rx: (), _tx: () }, self._rx, self._tx)
+                }
+            }
+
             impl<Rx, Tx> Read<u8> for $type<Enabled, Rx, Tx>
             where
                 Rx: UartRx<$type<Enabled, Rx, Tx>>,
                 Tx: UartTx<$type<Enabled, Rx, Tx>>,
             {
-                type Error = ();
+                type Error = Error;
 
-                fn try_read(&mut self) -> Result<u8, nb::Error<()>> {
-                    if self._uart.lsr.read().rdr().bit() {
+                fn try_read(&mut self) -> nb::Result<u8, Error> {
+                    let lsr = self._uart.lsr.read();
+                    if lsr.oe().bit_is_set() {
+                        Err(nb::Error::Other(Error::Overrun))
+                    } else if lsr.bi().bit_is_set() || lsr.fe().bit_is_set() || lsr.pe().bit_is_set() {
+                        // Discard the erroneous character at the top of the FIFO
+                        let _ = self._uart.rbr().read();
+                        if lsr.bi().bit_is_set() {
+                            Err(nb::Error::Other(Error::Break))
+                        } else if lsr.fe().bit_is_set() {
+                            Err(nb::Error::Other(Error::Framing))
+                        } else {
+                            Err(nb::Error::Other(Error::Parity))
+                        }
+                    } else if lsr.rdr().bit() {
                         Ok(self._uart.rbr().read().rbr().bits())
                     } else {
                         Err(WouldBlock)
@@ -118,9 +174,9 @@ macro_rules! uarts {
                 Rx: UartRx<$type<Enabled, Rx, Tx>>,
                 Tx: UartTx<$type<Enabled, Rx, Tx>>,
             {
-                type Error = ();
+                type Error = Error;
 
-                fn try_write(&mut self, data: u8) -> Result<(), nb::Error<()>> {
+                fn try_write(&mut self, data: u8) -> nb::Result<(), Error> {
                     if self._uart.lsr.read().thre().bit() {
                         unsafe { self._uart.thr().write(|w| w.thr().bits(data)); }
                         Ok(())
@@ -129,8 +185,9 @@ macro_rules! uarts {
                     }
                 }
 
-                fn try_flush(&mut self) -> Result<(), nb::Error<()>> {
-                    if self._uart.lsr.read().thre().bit() {
+                fn try_flush(&mut self) -> nb::Result<(), Error> {
+                    // The transmitter is empty once the last stop bit has left the shift register
+                    if self._uart.lsr.read().temt().bit() {
                         Ok(())
                     } else {
                         Err(WouldBlock)
@@ -138,18 +195,98 @@ macro_rules! uarts {
                 }
             }
 
+            impl<Rx, Tx> write::Default<u8> for $type<Enabled, Rx, Tx>
+            where
+                Rx: UartRx<$type<Enabled, Rx, Tx>>,
+                Tx: UartTx<$type<Enabled, Rx, Tx>>,
+            {}
+
+            impl<Rx, Tx> $type<Enabled, Rx, Tx>
+            where
+                Rx: UartRx<$type<Enabled, Rx, Tx>>,
+                Tx: UartTx<$type<Enabled, Rx, Tx>>,
+            {
+                /// Writes all bytes of `buffer`, blocking until each byte has been queued.
+                pub fn write_all(&mut self, buffer: &[u8]) -> Result<(), Error> {
+                    self.try_bwrite_all(buffer)
+                }
+
+                /// Fills `buffer` with received bytes, blocking until it is full.
+                ///
+                /// Returns the first receive error. Bytes read up to the error are kept in `buffer`.
+                pub fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+                    for byte in buffer.iter_mut() {
+                        *byte = nb::block!(self.try_read())?;
+                    }
+                    Ok(())
+                }
+
+                /// Like `write_all`, but gives up with `Error::Timeout` once `timer` expires.
+                ///
+                /// # Arguments
+                ///
+                /// * buffer - The bytes to transmit.
+                /// * timer - A timer, e.g. one of `Timer0` to `Timer3`, used to bound the transfer.
+                /// * timeout - The time allowed for the whole transfer.
+                pub fn write_all_timeout<C, T>(&mut self, buffer: &[u8], timer: &mut C, timeout: T) -> Result<(), Error>
+                where
+                    C: CountDown,
+                    T: Into<C::Time>,
+                {
+                    timer.try_start(timeout).map_err(|_| Error::Timer)?;
+                    for byte in buffer {
+                        loop {
+                            match self.try_write(*byte) {
+                                Ok(()) => break,
+                                Err(nb::Error::Other(e)) => return Err(e),
+                                Err(WouldBlock) => Self::check_timeout(timer)?,
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+
+                /// Like `read_exact`, but gives up with `Error::Timeout` once `timer` expires.
+                ///
+                /// # Arguments
+                ///
+                /// * buffer - The buffer to fill with received bytes.
+                /// * timer - A timer, e.g. one of `Timer0` to `Timer3`, used to bound the transfer.
+                /// * timeout - The time allowed for the whole transfer.
+                pub fn read_exact_timeout<C, T>(&mut self, buffer: &mut [u8], timer: &mut C, timeout: T) -> Result<(), Error>
+                where
+                    C: CountDown,
+                    T: Into<C::Time>,
+                {
+                    timer.try_start(timeout).map_err(|_| Error::Timer)?;
+                    for byte in buffer.iter_mut() {
+                        *byte = loop {
+                            match self.try_read() {
+                                Ok(data) => break data,
+                                Err(nb::Error::Other(e)) => return Err(e),
+                                Err(WouldBlock) => Self::check_timeout(timer)?,
+                            }
+                        };
+                    }
+                    Ok(())
+                }
+
+                fn check_timeout<C: CountDown>(timer: &mut C) -> Result<(), Error> {
+                    match timer.try_wait() {
+                        Err(WouldBlock) => Ok(()),
+                        Ok(()) => Err(Error::Timeout),
+                        Err(nb::Error::Other(_)) => Err(Error::Timer),
+                    }
+                }
+            }
+
             impl<Rx, Tx> fmt::Write for $type<Enabled, Rx, Tx>
             where
                 Rx: UartRx<$type<Enabled, Rx, Tx>>,
                 Tx: UartTx<$type<Enabled, Rx, Tx>>,
             {
                 fn write_str(&mut self, s: &str) -> Result<(), fmt::Error> {
-                    let _ = s
-                        .as_bytes()
-                        .into_iter()
-                        .map(|c| nb::block!(self.try_write(*c)))
-                        .last();
-                    Ok(())
+                    self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
                 }
             }
         )*
@@ -164,73 +301,97 @@ uarts!(
     (Uart4, Uart4Rx, Uart4Tx, UART4, pcuart4),
 );
 
+impl<Rx, Tx> Uart1<Enabled, Rx, Tx>
+where
+    Rx: UartRx<Uart1<Enabled, Rx, Tx>>,
+    Tx: UartTx<Uart1<Enabled, Rx, Tx>>,
+{
+    /// Enables or disables the loopback mode of UART1.
+    ///
+    /// In loopback mode the transmitter output is internally connected to the receiver input
+    /// and the Tx pin is held in marking state. This allows a self-test of the UART without
+    /// any external wiring. UART1 is the only UART of the LPC178x/7x with a loopback mode.
+    ///
+    /// # Arguments
+    ///
+    /// * enabled - Whether transmitted data is looped back to the receiver.
+    pub fn set_loopback(&mut self, enabled: bool) {
+        self._uart.mcr.modify(|_, w| w.lms().bit(enabled));
+    }
+
+    /// Returns whether UART1 is in loopback mode.
+    pub fn is_loopback(&self) -> bool {
+        self._uart.mcr.read().lms().bit()
+    }
+}
+
 macro_rules! uart_rx {
-($(($pin: ident, $io: ident, $type: ident, $func: ident)),* $(,)?) => {
+($($(#[$attr: meta])* ($pin: ident, $io: ident, $type: ident, $function: ident, $func: ident)),* $(,)?) => {
     $(
-        impl<T, S, Rx, Tx> UartRx<$type<S, Rx, Tx>> for gpio::$pin<T>
+        gpio::pin_functions!($(#[$attr])* ($pin, $io, $function, $func));
+
+        $(#[$attr])*
+        impl<S, Rx, Tx> UartRx<$type<S, Rx, Tx>> for gpio::$pin<AltFunction<function::$function>>
         where
-            T: PinState,
             S: InitState,
-        {
-            fn into_uartrx(&self) {
-                unsafe {
-                    (*crate::pac::IOCON::ptr()).$io.write(|w| w.func().$func());
-                }
-            }
-        }
+        {}
     )*
     }
 }
 
 macro_rules! uart_tx {
-($(($pin: ident, $io: ident, $type: ident, $func: ident)),* $(,)?) => {
+($($(#[$attr: meta])* ($pin: ident, $io: ident, $type: ident, $function: ident, $func: ident)),* $(,)?) => {
     $(
-        impl<T, S, Rx, Tx> UartTx<$type<S, Rx, Tx>> for gpio::$pin<T>
+        gpio::pin_functions!($(#[$attr])* ($pin, $io, $function, $func));
+
+        $(#[$attr])*
+        impl<S, Rx, Tx> UartTx<$type<S, Rx, Tx>> for gpio::$pin<AltFunction<function::$function>>
         where
-            T: PinState,
             S: InitState,
-        {
-            fn into_uarttx(&self) {
-                unsafe {
-                    (*crate::pac::IOCON::ptr()).$io.write(|w| w.func().$func());
-                }
-            }
-        }
+        {}
     )*
     }
 }
 
 uart_rx!(
-    (P0_1, p0_1, Uart0, u0_rxd),
-    (P0_1, p0_1, Uart3, u3_rxd),
-    (P0_3, p0_3, Uart0, u0_rxd),
-    (P0_3, p0_3, Uart3, u3_rxd),
-    (P0_11, p0_11, Uart2, u2_rxd),
-    (P0_16, p0_16, Uart1, u1_rxd),
-    (P0_26, p0_26, Uart3, u3_rxd),
-    (P2_1, p2_1, Uart1, u1_rxd),
-    (P2_9, p2_9, Uart2, u2_rxd),
-    (P2_9, p2_9, Uart4, u4_rxd),
-    (P3_17, p3_17, Uart1, u1_rxd),
-    (P4_23, p4_23, Uart2, u2_rxd),
-    (P4_29, p4_29, Uart3, u3_rxd),
-    (P5_3, p5_3, Uart4, u4_rxd),
+    (P0_1, p0_1, Uart0, U0Rxd, u0_rxd),
+    (P0_1, p0_1, Uart3, U3Rxd, u3_rxd),
+    (P0_3, p0_3, Uart0, U0Rxd, u0_rxd),
+    (P0_3, p0_3, Uart3, U3Rxd, u3_rxd),
+    (P0_11, p0_11, Uart2, U2Rxd, u2_rxd),
+    (P0_16, p0_16, Uart1, U1Rxd, u1_rxd),
+    (P0_26, p0_26, Uart3, U3Rxd, u3_rxd),
+    (P2_1, p2_1, Uart1, U1Rxd, u1_rxd),
+    (P2_9, p2_9, Uart2, U2Rxd, u2_rxd),
+    (P2_9, p2_9, Uart4, U4Rxd, u4_rxd),
+    #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
+    (P3_17, p3_17, Uart1, U1Rxd, u1_rxd),
+    #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
+    (P4_23, p4_23, Uart2, U2Rxd, u2_rxd),
+    #[cfg(not(feature = "lqfp144"))]
+    (P4_29, p4_29, Uart3, U3Rxd, u3_rxd),
+    #[cfg(not(feature = "lqfp144"))]
+    (P5_3, p5_3, Uart4, U4Rxd, u4_rxd),
 );
 
 uart_tx!(
-    (P0_0, p0_0, Uart0, u0_txd),
-    (P0_0, p0_0, Uart3, u3_txd),
-    (P0_2, p0_2, Uart0, u0_txd),
-    (P0_2, p0_2, Uart3, u3_txd),
-    (P0_10, p0_10, Uart2, u2_txd),
-    (P0_15, p0_15, Uart1, u1_txd),
-    (P0_22, p0_22, Uart4, u4_txd),
-    (P0_25, p0_25, Uart3, u3_txd),
-    (P1_29, p1_29, Uart4, u4_txd),
-    (P2_0, p2_0, Uart1, u1_txd),
-    (P2_8, p2_8, Uart2, u2_txd),
-    (P3_16, p3_16, Uart1, u1_txd),
-    (P4_22, p4_22, Uart2, u2_txd),
-    (P4_28, p4_28, Uart3, u3_txd),
-    (P5_4, p5_4, Uart4, u4_txd),
+    (P0_0, p0_0, Uart0, U0Txd, u0_txd),
+    (P0_0, p0_0, Uart3, U3Txd, u3_txd),
+    (P0_2, p0_2, Uart0, U0Txd, u0_txd),
+    (P0_2, p0_2, Uart3, U3Txd, u3_txd),
+    (P0_10, p0_10, Uart2, U2Txd, u2_txd),
+    (P0_15, p0_15, Uart1, U1Txd, u1_txd),
+    (P0_22, p0_22, Uart4, U4Txd, u4_txd),
+    (P0_25, p0_25, Uart3, U3Txd, u3_txd),
+    (P1_29, p1_29, Uart4, U4Txd, u4_txd),
+    (P2_0, p2_0, Uart1, U1Txd, u1_txd),
+    (P2_8, p2_8, Uart2, U2Txd, u2_txd),
+    #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
+    (P3_16, p3_16, Uart1, U1Txd, u1_txd),
+    #[cfg(not(any(feature = "lqfp144", feature = "tfbga180")))]
+    (P4_22, p4_22, Uart2, U2Txd, u2_txd),
+    #[cfg(not(feature = "lqfp144"))]
+    (P4_28, p4_28, Uart3, U3Txd, u3_txd),
+    #[cfg(not(feature = "lqfp144"))]
+    (P5_4, p5_4, Uart4, U4Txd, u4_txd),
 );
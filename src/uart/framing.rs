@@ -0,0 +1,479 @@
+//! Packet framing on top of the UART HAL wrappers.
+//!
+//! A frame consists of the payload followed by a CRC-16/CCITT-FALSE checksum (big endian),
+//! encoded with either COBS or SLIP so that frame boundaries can be found in the byte stream.
+//! The encoders and decoders in `cobs` and `slip` as well as `crc16` do not touch any
+//! hardware and can be used on their own.
+
+use embedded_hal::serial::{Read, Write};
+
+/// Maximum payload length of a single frame.
+pub const MAX_PAYLOAD_LEN: usize = 256;
+
+/// Length of the checksum appended to every payload.
+const CRC_LEN: usize = 2;
+
+/// Maximum length of an encoded frame, excluding delimiters.
+pub const MAX_ENCODED_LEN: usize = 2 * (MAX_PAYLOAD_LEN + CRC_LEN);
+
+/// Byte stuffing used to delimit frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Consistent Overhead Byte Stuffing, frames are terminated by a zero byte.
+    Cobs,
+    /// Serial Line Internet Protocol (RFC 1055), frames are enclosed in `END` bytes.
+    Slip,
+}
+
+impl Encoding {
+    fn delimiter(self) -> u8 {
+        match self {
+            Encoding::Cobs => cobs::DELIMITER,
+            Encoding::Slip => slip::END,
+        }
+    }
+
+    fn encode(self, input: &[u8], output: &mut [u8]) -> Result<usize, CodecError> {
+        match self {
+            Encoding::Cobs => cobs::encode(input, output),
+            Encoding::Slip => slip::encode(input, output),
+        }
+    }
+
+    fn decode(self, input: &[u8], output: &mut [u8]) -> Result<usize, CodecError> {
+        match self {
+            Encoding::Cobs => cobs::decode(input, output),
+            Encoding::Slip => slip::decode(input, output),
+        }
+    }
+}
+
+/// Errors of the COBS and SLIP encoders and decoders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    /// The output buffer is too small for the result.
+    BufferFull,
+    /// The input is not a validly encoded frame.
+    Malformed,
+}
+
+/// Errors reported by `Framed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The underlying serial line reported an error.
+    Serial(E),
+    /// The frame does not fit into the frame or payload buffer.
+    BufferFull,
+    /// The received frame is not validly encoded.
+    Malformed,
+    /// The checksum of the received frame does not match its payload.
+    Crc,
+}
+
+impl<E> From<CodecError> for Error<E> {
+    fn from(error: CodecError) -> Self {
+        match error {
+            CodecError::BufferFull => Error::BufferFull,
+            CodecError::Malformed => Error::Malformed,
+        }
+    }
+}
+
+fn put(output: &mut [u8], index: usize, byte: u8) -> Result<(), CodecError> {
+    *output.get_mut(index).ok_or(CodecError::BufferFull)? = byte;
+    Ok(())
+}
+
+/// Computes the CRC-16/CCITT-FALSE checksum (polynomial 0x1021, initial value 0xFFFF) of `data`.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Consistent Overhead Byte Stuffing.
+pub mod cobs {
+    use super::{put, CodecError};
+
+    /// Byte terminating every COBS encoded frame.
+    pub const DELIMITER: u8 = 0x00;
+
+    /// Encodes `input` into `output`, not including the terminating `DELIMITER`.
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn encode(input: &[u8], output: &mut [u8]) -> Result<usize, CodecError> {
+        let mut code_index = 0;
+        let mut index = 1;
+        let mut code = 1u8;
+        for (position, byte) in input.iter().enumerate() {
+            if *byte != 0 {
+                put(output, index, *byte)?;
+                index += 1;
+                code += 1;
+            }
+            if *byte == 0 || code == 0xFF {
+                put(output, code_index, code)?;
+                code = 1;
+                // A full block ending the input is not followed by another block
+                if *byte != 0 && position + 1 == input.len() {
+                    return Ok(index);
+                }
+                code_index = index;
+                index += 1;
+            }
+        }
+        put(output, code_index, code)?;
+        Ok(index)
+    }
+
+    /// Decodes the COBS encoded `input`, not including the terminating `DELIMITER`, into `output`.
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn decode(input: &[u8], output: &mut [u8]) -> Result<usize, CodecError> {
+        let mut index = 0;
+        let mut length = 0;
+        while index < input.len() {
+            let code = input[index];
+            if code == 0 {
+                return Err(CodecError::Malformed);
+            }
+            index += 1;
+            for _ in 1..code {
+                let byte = *input.get(index).ok_or(CodecError::Malformed)?;
+                if byte == 0 {
+                    return Err(CodecError::Malformed);
+                }
+                put(output, length, byte)?;
+                length += 1;
+                index += 1;
+            }
+            if code < 0xFF && index < input.len() {
+                put(output, length, 0)?;
+                length += 1;
+            }
+        }
+        Ok(length)
+    }
+}
+
+/// Serial Line Internet Protocol byte stuffing (RFC 1055).
+pub mod slip {
+    use super::{put, CodecError};
+
+    /// Byte delimiting SLIP frames.
+    pub const END: u8 = 0xC0;
+    /// Byte introducing an escape sequence.
+    pub const ESC: u8 = 0xDB;
+    /// Escaped replacement of `END`.
+    pub const ESC_END: u8 = 0xDC;
+    /// Escaped replacement of `ESC`.
+    pub const ESC_ESC: u8 = 0xDD;
+
+    /// Encodes `input` into `output`, not including the enclosing `END` bytes.
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn encode(input: &[u8], output: &mut [u8]) -> Result<usize, CodecError> {
+        let mut length = 0;
+        for byte in input {
+            match *byte {
+                END => {
+                    put(output, length, ESC)?;
+                    put(output, length + 1, ESC_END)?;
+                    length += 2;
+                }
+                ESC => {
+                    put(output, length, ESC)?;
+                    put(output, length + 1, ESC_ESC)?;
+                    length += 2;
+                }
+                byte => {
+                    put(output, length, byte)?;
+                    length += 1;
+                }
+            }
+        }
+        Ok(length)
+    }
+
+    /// Decodes the SLIP encoded `input`, not including the enclosing `END` bytes, into `output`.
+    ///
+    /// Returns the number of bytes written to `output`.
+    pub fn decode(input: &[u8], output: &mut [u8]) -> Result<usize, CodecError> {
+        let mut length = 0;
+        let mut bytes = input.iter();
+        while let Some(byte) = bytes.next() {
+            let decoded = match *byte {
+                END => return Err(CodecError::Malformed),
+                ESC => match bytes.next() {
+                    Some(&ESC_END) => END,
+                    Some(&ESC_ESC) => ESC,
+                    _ => return Err(CodecError::Malformed),
+                },
+                byte => byte,
+            };
+            put(output, length, decoded)?;
+            length += 1;
+        }
+        Ok(length)
+    }
+}
+
+/// Sends and receives checksummed, encoded frames over a serial line.
+pub struct Framed<S> {
+    serial: S,
+    encoding: Encoding,
+    buffer: [u8; MAX_ENCODED_LEN],
+    length: usize,
+    overflow: bool,
+}
+
+impl<S, E> Framed<S>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E>,
+{
+    /// Wraps a serial line, e.g. an enabled `Uart0`, for framed transfers.
+    ///
+    /// # Arguments
+    ///
+    /// * serial - The serial line frames are sent and received on.
+    /// * encoding - The byte stuffing used to delimit frames.
+    pub fn new(serial: S, encoding: Encoding) -> Self {
+        Framed {
+            serial,
+            encoding,
+            buffer: [0; MAX_ENCODED_LEN],
+            length: 0,
+            overflow: false,
+        }
+    }
+
+    /// Releases the wrapped serial line. Any partially received frame is dropped.
+    pub fn free(self) -> S {
+        self.serial
+    }
+
+    /// Encodes `payload` as a frame and transmits it, blocking until it has been sent.
+    pub fn write_frame(&mut self, payload: &[u8]) -> Result<(), Error<E>> {
+        if payload.len() > MAX_PAYLOAD_LEN {
+            return Err(Error::BufferFull);
+        }
+        let mut raw = [0u8; MAX_PAYLOAD_LEN + CRC_LEN];
+        raw[..payload.len()].copy_from_slice(payload);
+        let crc = crc16(payload);
+        raw[payload.len()] = (crc >> 8) as u8;
+        raw[payload.len() + 1] = crc as u8;
+
+        let mut encoded = [0u8; MAX_ENCODED_LEN];
+        let length = self
+            .encoding
+            .encode(&raw[..payload.len() + CRC_LEN], &mut encoded)?;
+
+        let delimiter = self.encoding.delimiter();
+        if self.encoding == Encoding::Slip {
+            // A leading END flushes any line noise received by the peer
+            self.write_byte(delimiter)?;
+        }
+        for byte in &encoded[..length] {
+            self.write_byte(*byte)?;
+        }
+        self.write_byte(delimiter)?;
+        nb::block!(self.serial.try_flush()).map_err(Error::Serial)
+    }
+
+    /// Receives a frame and copies its payload to `payload`.
+    ///
+    /// Returns `WouldBlock` until a complete frame has been received, so it can be polled from
+    /// an interrupt or main loop. Frames that are corrupted or too long are dropped and reported
+    /// as errors; the next call starts with the following frame.
+    ///
+    /// # Return
+    ///
+    /// * The length of the received payload.
+    pub fn read_frame(&mut self, payload: &mut [u8]) -> nb::Result<usize, Error<E>> {
+        loop {
+            let byte = self
+                .serial
+                .try_read()
+                .map_err(|e| e.map(Error::Serial))?;
+            if byte != self.encoding.delimiter() {
+                if self.length < self.buffer.len() {
+                    self.buffer[self.length] = byte;
+                    self.length += 1;
+                } else {
+                    self.overflow = true;
+                }
+                continue;
+            }
+            if self.length == 0 && !self.overflow {
+                // Ignore empty frames, e.g. back to back SLIP END bytes
+                continue;
+            }
+            let length = self.length;
+            self.length = 0;
+            if self.overflow {
+                self.overflow = false;
+                return Err(nb::Error::Other(Error::BufferFull));
+            }
+            return self.decode(length, payload).map_err(nb::Error::Other);
+        }
+    }
+
+    fn decode(&self, length: usize, payload: &mut [u8]) -> Result<usize, Error<E>> {
+        let mut raw = [0u8; MAX_PAYLOAD_LEN + CRC_LEN];
+        let length = self.encoding.decode(&self.buffer[..length], &mut raw)?;
+        if length < CRC_LEN {
+            return Err(Error::Malformed);
+        }
+        let data = &raw[..length - CRC_LEN];
+        let crc = ((raw[length - 2] as u16) << 8) | raw[length - 1] as u16;
+        if crc16(data) != crc {
+            return Err(Error::Crc);
+        }
+        payload
+            .get_mut(..data.len())
+            .ok_or(Error::BufferFull)?
+            .copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error<E>> {
+        nb::block!(self.serial.try_write(byte)).map_err(Error::Serial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cobs_round_trip(payload: &[u8]) -> usize {
+        let mut encoded = [0xAAu8; MAX_ENCODED_LEN];
+        let mut decoded = [0u8; MAX_ENCODED_LEN];
+        let length = cobs::encode(payload, &mut encoded).unwrap();
+        assert!(!encoded[..length].contains(&cobs::DELIMITER));
+        let decoded_length = cobs::decode(&encoded[..length], &mut decoded).unwrap();
+        assert_eq!(&decoded[..decoded_length], payload);
+        length
+    }
+
+    fn slip_round_trip(payload: &[u8]) -> usize {
+        let mut encoded = [0u8; MAX_ENCODED_LEN];
+        let mut decoded = [0u8; MAX_ENCODED_LEN];
+        let length = slip::encode(payload, &mut encoded).unwrap();
+        assert!(!encoded[..length].contains(&slip::END));
+        let decoded_length = slip::decode(&encoded[..length], &mut decoded).unwrap();
+        assert_eq!(&decoded[..decoded_length], payload);
+        length
+    }
+
+    #[test]
+    fn crc16_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[]), 0xFFFF);
+    }
+
+    #[test]
+    fn cobs_examples() {
+        let mut encoded = [0u8; 8];
+        let length = cobs::encode(&[], &mut encoded).unwrap();
+        assert_eq!(&encoded[..length], &[0x01]);
+        let length = cobs::encode(&[0x00], &mut encoded).unwrap();
+        assert_eq!(&encoded[..length], &[0x01, 0x01]);
+        let length = cobs::encode(&[0x11, 0x22, 0x00, 0x33], &mut encoded).unwrap();
+        assert_eq!(&encoded[..length], &[0x03, 0x11, 0x22, 0x02, 0x33]);
+    }
+
+    #[test]
+    fn cobs_round_trips() {
+        cobs_round_trip(&[]);
+        cobs_round_trip(&[0x00]);
+        cobs_round_trip(&[0x00, 0x00]);
+        cobs_round_trip(&[0x11, 0x00, 0x00, 0x22]);
+        cobs_round_trip(&[0x01, 0x02, 0x03, 0x00]);
+    }
+
+    #[test]
+    fn cobs_run_boundaries() {
+        let mut payload = [0u8; 256];
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte = (i % 255) as u8 + 1;
+        }
+        // 254 non-zero bytes fill exactly one block
+        assert_eq!(cobs_round_trip(&payload[..254]), 255);
+        // 255 non-zero bytes need a second block
+        assert_eq!(cobs_round_trip(&payload[..255]), 257);
+        assert_eq!(cobs_round_trip(&payload[..256]), 258);
+
+        let mut encoded = [0u8; 260];
+        cobs::encode(&payload[..254], &mut encoded).unwrap();
+        assert_eq!(encoded[0], 0xFF);
+        cobs::encode(&payload[..255], &mut encoded).unwrap();
+        assert_eq!((encoded[0], encoded[255]), (0xFF, 0x02));
+
+        payload[254] = 0;
+        assert_eq!(cobs_round_trip(&payload[..255]), 257);
+    }
+
+    #[test]
+    fn cobs_rejects_malformed_input() {
+        let mut decoded = [0u8; 8];
+        assert_eq!(
+            cobs::decode(&[0x03, 0x11], &mut decoded),
+            Err(CodecError::Malformed)
+        );
+        assert_eq!(
+            cobs::decode(&[0x02, 0x00], &mut decoded),
+            Err(CodecError::Malformed)
+        );
+    }
+
+    #[test]
+    fn slip_escapes_end_and_esc() {
+        let mut encoded = [0u8; 8];
+        let length = slip::encode(&[0x01, slip::END, slip::ESC, 0x02], &mut encoded).unwrap();
+        assert_eq!(
+            &encoded[..length],
+            &[0x01, slip::ESC, slip::ESC_END, slip::ESC, slip::ESC_ESC, 0x02]
+        );
+    }
+
+    #[test]
+    fn slip_round_trips() {
+        assert_eq!(slip_round_trip(&[]), 0);
+        slip_round_trip(&[slip::END]);
+        slip_round_trip(&[slip::ESC, slip::ESC_END, slip::ESC_ESC]);
+        slip_round_trip(&[slip::END, slip::END, 0x00, slip::ESC]);
+    }
+
+    #[test]
+    fn slip_rejects_malformed_input() {
+        let mut decoded = [0u8; 8];
+        assert_eq!(
+            slip::decode(&[slip::ESC, 0x01], &mut decoded),
+            Err(CodecError::Malformed)
+        );
+        assert_eq!(slip::decode(&[slip::ESC], &mut decoded), Err(CodecError::Malformed));
+        assert_eq!(slip::decode(&[slip::END], &mut decoded), Err(CodecError::Malformed));
+    }
+
+    #[test]
+    fn encoders_report_full_buffer() {
+        let mut encoded = [0u8; 2];
+        assert_eq!(
+            cobs::encode(&[1, 2, 3], &mut encoded),
+            Err(CodecError::BufferFull)
+        );
+        assert_eq!(
+            slip::encode(&[slip::END, 1], &mut encoded),
+            Err(CodecError::BufferFull)
+        );
+    }
+}
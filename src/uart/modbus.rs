@@ -0,0 +1,1102 @@
+//! Modbus RTU master and slave on top of the UART HAL wrappers.
+//!
+//! Frames on the serial line are delimited by at least 3.5 character times of silence. The end
+//! of a received frame is detected with the character timeout of the UART receiver, one of the
+//! HAL timers (`Timer0` to `Timer3`) measures the response timeout and the silence after a
+//! transmitted frame. Half-duplex RS-485 transceivers are switched with a `Direction`. The PDU
+//! handling in `pdu` does not touch any hardware and can be used on its own.
+
+use super::{Enabled, Uart0, Uart1, Uart2, Uart3, Uart4, UartRx, UartTx};
+use embedded_hal::digital::OutputPin;
+use embedded_hal::serial::{Read, Write};
+use embedded_hal::timer::CountDown;
+use embedded_time::duration::Microseconds;
+use nb::Error::WouldBlock;
+
+pub use pdu::{Exception, Handler, Request};
+
+/// Maximum length of an RTU frame (address, PDU and CRC).
+pub const MAX_ADU_LEN: usize = 256;
+
+/// Maximum length of a PDU (function code and data).
+pub const MAX_PDU_LEN: usize = 253;
+
+/// Unit address of broadcast requests, which are never answered.
+pub const BROADCAST: u8 = 0;
+
+/// Errors reported by the Modbus master and slave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The underlying serial line reported an error.
+    Serial(E),
+    /// The timer could not be started or waited for.
+    Timer,
+    /// The direction of the transceiver could not be switched.
+    Direction,
+    /// The received frame is longer than `MAX_ADU_LEN`.
+    BufferFull,
+    /// The received frame is too short or its CRC does not match.
+    Crc,
+    /// The slave did not respond in time.
+    Timeout,
+    /// The request can not be encoded, e.g. because it addresses too many items.
+    InvalidRequest,
+    /// The response does not match the request.
+    UnexpectedResponse,
+    /// The slave answered with an exception response.
+    Exception(Exception),
+}
+
+/// Computes the Modbus CRC-16 (polynomial 0xA001 reflected, initial value 0xFFFF) of `data`.
+///
+/// The CRC is transmitted low byte first.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, byte| crc16_update(crc, *byte))
+}
+
+fn crc16_update(crc: u16, byte: u8) -> u16 {
+    let mut crc = crc ^ byte as u16;
+    for _ in 0..8 {
+        crc = if crc & 0x0001 != 0 {
+            (crc >> 1) ^ 0xA001
+        } else {
+            crc >> 1
+        };
+    }
+    crc
+}
+
+/// Returns the minimum silence between two frames for `baudrate`.
+///
+/// This is 3.5 characters of 11 bits each, or the fixed 1750 µs recommended by the Modbus
+/// serial line specification for baudrates above 19200. The fixed value is also used for a
+/// baudrate of 0.
+pub fn frame_silence(baudrate: u32) -> Microseconds<u32> {
+    if baudrate == 0 || baudrate > 19_200 {
+        Microseconds(1_750)
+    } else {
+        Microseconds(38_500_000 / baudrate)
+    }
+}
+
+/// Receive FIFO trigger level set by `RxTimeout::enable_rx_events`, in characters.
+pub const RX_TRIGGER_LEVEL: usize = 8;
+
+/// Pending receiver event of a UART.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RxEvent {
+    /// No event is pending.
+    None,
+    /// The character at the top of the receive FIFO has an error.
+    Error,
+    /// The receive FIFO holds at least `RX_TRIGGER_LEVEL` characters.
+    Data,
+    /// The receive FIFO is not empty and no character was received for 3.5 to 4.5 character
+    /// times, which ends a frame.
+    Timeout,
+}
+
+/// A serial line reporting receiver events, including the character timeout.
+pub trait RxTimeout {
+    /// Sets the receive FIFO trigger level to `RX_TRIGGER_LEVEL` and enables the receiver events.
+    fn enable_rx_events(&mut self);
+
+    /// Returns the pending receiver event of the highest priority.
+    fn rx_event(&mut self) -> RxEvent;
+}
+
+macro_rules! rx_timeout {
+    ($($type: ident),* $(,)?) => {
+        $(
+            /// The receiver events are reported by the UART interrupt identification register.
+            /// Enabling them enables the receive data interrupt of the UART, which only reaches
+            /// the CPU if the UART interrupt is unmasked in the NVIC.
+            impl<Rx, Tx> RxTimeout for $type<Enabled, Rx, Tx>
+            where
+                Rx: UartRx<$type<Enabled, Rx, Tx>>,
+                Tx: UartTx<$type<Enabled, Rx, Tx>>,
+            {
+                fn enable_rx_events(&mut self) {
+                    // The trigger level 2 selects 8 characters
+                    self._uart.fcr().write(|w| unsafe { w.fifoen().set_bit().rxtriglvl().bits(2) });
+                    self._uart.ier().modify(|_, w| w.rbrie().set_bit());
+                }
+
+                fn rx_event(&mut self) -> RxEvent {
+                    let iir = self._uart.iir().read();
+                    if iir.intstatus().bit_is_set() {
+                        return RxEvent::None;
+                    }
+                    match iir.intid().bits() {
+                        0b011 => RxEvent::Error,
+                        0b010 => RxEvent::Data,
+                        0b110 => RxEvent::Timeout,
+                        _ => RxEvent::None,
+                    }
+                }
+            }
+        )*
+    }
+}
+
+rx_timeout!(Uart0, Uart1, Uart2, Uart3, Uart4);
+
+/// Switches the transceiver of a half-duplex line between receiving and transmitting.
+pub trait Direction {
+    type Error;
+
+    /// Enables the driver of the transceiver if `transmit` is true, the receiver otherwise.
+    fn set_transmit(&mut self, transmit: bool) -> Result<(), Self::Error>;
+}
+
+/// No direction control, for full-duplex lines and transceivers that switch on their own.
+impl Direction for () {
+    type Error = core::convert::Infallible;
+
+    fn set_transmit(&mut self, _transmit: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// The driver enable (DE) pin of an RS-485 transceiver, driven high while transmitting.
+///
+/// The receiver enable pin (/RE) is usually connected to the same pin.
+pub struct DriverEnable<P>(pub P);
+
+impl<P: OutputPin> Direction for DriverEnable<P> {
+    type Error = P::Error;
+
+    fn set_transmit(&mut self, transmit: bool) -> Result<(), Self::Error> {
+        if transmit {
+            self.0.try_set_high()
+        } else {
+            self.0.try_set_low()
+        }
+    }
+}
+
+/// Modbus protocol data units, independent of the transport.
+pub mod pdu {
+    pub const READ_COILS: u8 = 0x01;
+    pub const READ_DISCRETE_INPUTS: u8 = 0x02;
+    pub const READ_HOLDING_REGISTERS: u8 = 0x03;
+    pub const READ_INPUT_REGISTERS: u8 = 0x04;
+    pub const WRITE_SINGLE_COIL: u8 = 0x05;
+    pub const WRITE_SINGLE_REGISTER: u8 = 0x06;
+    pub const WRITE_MULTIPLE_COILS: u8 = 0x0F;
+    pub const WRITE_MULTIPLE_REGISTERS: u8 = 0x10;
+
+    /// Bit set in the function code of exception responses.
+    pub const EXCEPTION_FLAG: u8 = 0x80;
+
+    const MAX_READ_BITS: u16 = 2000;
+    const MAX_READ_REGISTERS: u16 = 125;
+    const MAX_WRITE_BITS: u16 = 1968;
+    const MAX_WRITE_REGISTERS: u16 = 123;
+
+    /// Exception codes of exception responses.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Exception {
+        IllegalFunction = 0x01,
+        IllegalDataAddress = 0x02,
+        IllegalDataValue = 0x03,
+        ServerDeviceFailure = 0x04,
+        Acknowledge = 0x05,
+        ServerDeviceBusy = 0x06,
+        MemoryParityError = 0x08,
+        GatewayPathUnavailable = 0x0A,
+        GatewayTargetFailedToRespond = 0x0B,
+    }
+
+    impl Exception {
+        /// Converts an exception code received in an exception response.
+        pub fn from_code(code: u8) -> Option<Exception> {
+            match code {
+                0x01 => Some(Exception::IllegalFunction),
+                0x02 => Some(Exception::IllegalDataAddress),
+                0x03 => Some(Exception::IllegalDataValue),
+                0x04 => Some(Exception::ServerDeviceFailure),
+                0x05 => Some(Exception::Acknowledge),
+                0x06 => Some(Exception::ServerDeviceBusy),
+                0x08 => Some(Exception::MemoryParityError),
+                0x0A => Some(Exception::GatewayPathUnavailable),
+                0x0B => Some(Exception::GatewayTargetFailedToRespond),
+                _ => None,
+            }
+        }
+    }
+
+    /// A request as sent by a master.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Request<'a> {
+        ReadCoils { address: u16, quantity: u16 },
+        ReadDiscreteInputs { address: u16, quantity: u16 },
+        ReadHoldingRegisters { address: u16, quantity: u16 },
+        ReadInputRegisters { address: u16, quantity: u16 },
+        WriteSingleCoil { address: u16, value: bool },
+        WriteSingleRegister { address: u16, value: u16 },
+        WriteMultipleCoils { address: u16, values: &'a [bool] },
+        WriteMultipleRegisters { address: u16, values: &'a [u16] },
+    }
+
+    impl<'a> Request<'a> {
+        /// Returns the function code of the request.
+        pub fn function(&self) -> u8 {
+            match self {
+                Request::ReadCoils { .. } => READ_COILS,
+                Request::ReadDiscreteInputs { .. } => READ_DISCRETE_INPUTS,
+                Request::ReadHoldingRegisters { .. } => READ_HOLDING_REGISTERS,
+                Request::ReadInputRegisters { .. } => READ_INPUT_REGISTERS,
+                Request::WriteSingleCoil { .. } => WRITE_SINGLE_COIL,
+                Request::WriteSingleRegister { .. } => WRITE_SINGLE_REGISTER,
+                Request::WriteMultipleCoils { .. } => WRITE_MULTIPLE_COILS,
+                Request::WriteMultipleRegisters { .. } => WRITE_MULTIPLE_REGISTERS,
+            }
+        }
+
+        /// Encodes the request PDU into `output`.
+        ///
+        /// # Return
+        ///
+        /// * The length of the PDU, or `None` if the quantity of items is out of range or
+        ///   `output` is too small.
+        pub fn encode(&self, output: &mut [u8]) -> Option<usize> {
+            let (address, value) = match *self {
+                Request::ReadCoils { address, quantity }
+                | Request::ReadDiscreteInputs { address, quantity } => {
+                    check_quantity(quantity, MAX_READ_BITS)?;
+                    (address, quantity)
+                }
+                Request::ReadHoldingRegisters { address, quantity }
+                | Request::ReadInputRegisters { address, quantity } => {
+                    check_quantity(quantity, MAX_READ_REGISTERS)?;
+                    (address, quantity)
+                }
+                Request::WriteSingleCoil { address, value } => {
+                    (address, if value { 0xFF00 } else { 0x0000 })
+                }
+                Request::WriteSingleRegister { address, value } => (address, value),
+                Request::WriteMultipleCoils { address, values } => {
+                    (address, check_quantity(values.len() as u16, MAX_WRITE_BITS)?)
+                }
+                Request::WriteMultipleRegisters { address, values } => {
+                    (address, check_quantity(values.len() as u16, MAX_WRITE_REGISTERS)?)
+                }
+            };
+            let header = output.get_mut(..5)?;
+            header[0] = self.function();
+            header[1..3].copy_from_slice(&address.to_be_bytes());
+            header[3..5].copy_from_slice(&value.to_be_bytes());
+
+            match *self {
+                Request::WriteMultipleCoils { values, .. } => {
+                    let byte_count = (values.len() + 7) / 8;
+                    let data = output.get_mut(5..6 + byte_count)?;
+                    data[0] = byte_count as u8;
+                    pack_bits(values, &mut data[1..]);
+                    Some(6 + byte_count)
+                }
+                Request::WriteMultipleRegisters { values, .. } => {
+                    let byte_count = values.len() * 2;
+                    let data = output.get_mut(5..6 + byte_count)?;
+                    data[0] = byte_count as u8;
+                    for (chunk, value) in data[1..].chunks_mut(2).zip(values) {
+                        chunk.copy_from_slice(&value.to_be_bytes());
+                    }
+                    Some(6 + byte_count)
+                }
+                _ => Some(5),
+            }
+        }
+    }
+
+    /// The data model of a Modbus slave.
+    ///
+    /// Every method handles a single item. The default implementations reject the access
+    /// with `Exception::IllegalFunction`. Requests writing multiple items stop at the first
+    /// item that is rejected; items written before it keep their new value.
+    pub trait Handler {
+        fn read_coil(&mut self, _address: u16) -> Result<bool, Exception> {
+            Err(Exception::IllegalFunction)
+        }
+
+        fn read_discrete_input(&mut self, _address: u16) -> Result<bool, Exception> {
+            Err(Exception::IllegalFunction)
+        }
+
+        fn read_holding_register(&mut self, _address: u16) -> Result<u16, Exception> {
+            Err(Exception::IllegalFunction)
+        }
+
+        fn read_input_register(&mut self, _address: u16) -> Result<u16, Exception> {
+            Err(Exception::IllegalFunction)
+        }
+
+        fn write_coil(&mut self, _address: u16, _value: bool) -> Result<(), Exception> {
+            Err(Exception::IllegalFunction)
+        }
+
+        fn write_holding_register(&mut self, _address: u16, _value: u16) -> Result<(), Exception> {
+            Err(Exception::IllegalFunction)
+        }
+    }
+
+    /// Processes a request PDU with `handler` and encodes the response PDU into `response`.
+    ///
+    /// Failed requests are answered with an exception response.
+    ///
+    /// # Arguments
+    ///
+    /// * handler - The data model the request is executed on.
+    /// * request - The request PDU, starting with the function code.
+    /// * response - The buffer for the response PDU, at least `MAX_PDU_LEN` bytes long.
+    ///
+    /// # Return
+    ///
+    /// * The length of the response PDU, or 0 if `response` can not hold an exception response.
+    pub fn process<H: Handler>(handler: &mut H, request: &[u8], response: &mut [u8]) -> usize {
+        match execute(handler, request, response) {
+            Ok(length) => length,
+            Err(exception) => match response.get_mut(..2) {
+                Some(response) => {
+                    response[0] = request.first().copied().unwrap_or(0) | EXCEPTION_FLAG;
+                    response[1] = exception as u8;
+                    2
+                }
+                None => 0,
+            },
+        }
+    }
+
+    fn execute<H: Handler>(
+        handler: &mut H,
+        request: &[u8],
+        response: &mut [u8],
+    ) -> Result<usize, Exception> {
+        if response.len() < super::MAX_PDU_LEN {
+            return Err(Exception::ServerDeviceFailure);
+        }
+        let function = *request.first().ok_or(Exception::IllegalFunction)?;
+        let address = read_u16(request, 1);
+        let value = read_u16(request, 3);
+        let item = |offset: u16| {
+            address?
+                .checked_add(offset)
+                .ok_or(Exception::IllegalDataAddress)
+        };
+
+        match function {
+            READ_COILS | READ_DISCRETE_INPUTS => {
+                let quantity = check_quantity(value?, MAX_READ_BITS)
+                    .ok_or(Exception::IllegalDataValue)?;
+                let byte_count = (quantity as usize + 7) / 8;
+                response[0] = function;
+                response[1] = byte_count as u8;
+                for byte in &mut response[2..2 + byte_count] {
+                    *byte = 0;
+                }
+                for i in 0..quantity {
+                    let bit = if function == READ_COILS {
+                        handler.read_coil(item(i)?)?
+                    } else {
+                        handler.read_discrete_input(item(i)?)?
+                    };
+                    if bit {
+                        response[2 + i as usize / 8] |= 1 << (i % 8);
+                    }
+                }
+                Ok(2 + byte_count)
+            }
+            READ_HOLDING_REGISTERS | READ_INPUT_REGISTERS => {
+                let quantity = check_quantity(value?, MAX_READ_REGISTERS)
+                    .ok_or(Exception::IllegalDataValue)?;
+                response[0] = function;
+                response[1] = (quantity * 2) as u8;
+                for i in 0..quantity {
+                    let register = if function == READ_HOLDING_REGISTERS {
+                        handler.read_holding_register(item(i)?)?
+                    } else {
+                        handler.read_input_register(item(i)?)?
+                    };
+                    let offset = 2 + 2 * i as usize;
+                    response[offset..offset + 2].copy_from_slice(&register.to_be_bytes());
+                }
+                Ok(2 + 2 * quantity as usize)
+            }
+            WRITE_SINGLE_COIL => {
+                let coil = match value? {
+                    0xFF00 => true,
+                    0x0000 => false,
+                    _ => return Err(Exception::IllegalDataValue),
+                };
+                handler.write_coil(item(0)?, coil)?;
+                response[..5].copy_from_slice(&request[..5]);
+                Ok(5)
+            }
+            WRITE_SINGLE_REGISTER => {
+                handler.write_holding_register(item(0)?, value?)?;
+                response[..5].copy_from_slice(&request[..5]);
+                Ok(5)
+            }
+            WRITE_MULTIPLE_COILS => {
+                let quantity = check_quantity(value?, MAX_WRITE_BITS)
+                    .ok_or(Exception::IllegalDataValue)?;
+                let data = multiple_write_data(request, (quantity as usize + 7) / 8)?;
+                for i in 0..quantity {
+                    let bit = data[i as usize / 8] & (1 << (i % 8)) != 0;
+                    handler.write_coil(item(i)?, bit)?;
+                }
+                response[..5].copy_from_slice(&request[..5]);
+                Ok(5)
+            }
+            WRITE_MULTIPLE_REGISTERS => {
+                let quantity = check_quantity(value?, MAX_WRITE_REGISTERS)
+                    .ok_or(Exception::IllegalDataValue)?;
+                let data = multiple_write_data(request, quantity as usize * 2)?;
+                for (i, chunk) in (0..quantity).zip(data.chunks(2)) {
+                    let register = u16::from_be_bytes([chunk[0], chunk[1]]);
+                    handler.write_holding_register(item(i)?, register)?;
+                }
+                response[..5].copy_from_slice(&request[..5]);
+                Ok(5)
+            }
+            _ => Err(Exception::IllegalFunction),
+        }
+    }
+
+    fn read_u16(data: &[u8], offset: usize) -> Result<u16, Exception> {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or(Exception::IllegalDataValue)
+    }
+
+    fn check_quantity(quantity: u16, max: u16) -> Option<u16> {
+        if (1..=max).contains(&quantity) {
+            Some(quantity)
+        } else {
+            None
+        }
+    }
+
+    fn multiple_write_data(request: &[u8], byte_count: usize) -> Result<&[u8], Exception> {
+        if request.get(5).copied() != Some(byte_count as u8) {
+            return Err(Exception::IllegalDataValue);
+        }
+        request
+            .get(6..6 + byte_count)
+            .ok_or(Exception::IllegalDataValue)
+    }
+
+    /// Packs `bits` LSB first into `bytes`.
+    pub(super) fn pack_bits(bits: &[bool], bytes: &mut [u8]) {
+        for byte in bytes.iter_mut() {
+            *byte = 0;
+        }
+        for (i, bit) in bits.iter().enumerate() {
+            if *bit {
+                bytes[i / 8] |= 1 << (i % 8);
+            }
+        }
+    }
+}
+
+/// RTU framing of a serial line, using the character timeout of the UART to detect the end of
+/// received frames.
+pub struct Rtu<S, T, D = ()> {
+    serial: S,
+    timer: T,
+    direction: D,
+    silence: Microseconds<u32>,
+    buffer: [u8; MAX_ADU_LEN],
+    length: usize,
+    frame_length: usize,
+    receiving: bool,
+    corrupted: bool,
+}
+
+impl<S, T, E> Rtu<S, T>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E> + RxTimeout,
+    T: CountDown<Time = Microseconds<u32>>,
+{
+    /// Creates the RTU transport for a full-duplex line or a transceiver switching on its own.
+    ///
+    /// # Arguments
+    ///
+    /// * serial - An enabled UART, configured for `baudrate`.
+    /// * timer - An enabled timer used for the silence after transmitted frames and the
+    ///   response timeout of a master.
+    /// * baudrate - The baudrate of `serial`, used to compute the 3.5 character silence.
+    pub fn new(serial: S, timer: T, baudrate: u32) -> Self {
+        Rtu::with_direction(serial, timer, baudrate, ())
+    }
+}
+
+impl<S, T, D, E> Rtu<S, T, D>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E> + RxTimeout,
+    T: CountDown<Time = Microseconds<u32>>,
+    D: Direction,
+{
+    /// Creates the RTU transport for a half-duplex line.
+    ///
+    /// # Arguments
+    ///
+    /// * serial - An enabled UART, configured for `baudrate`.
+    /// * timer - An enabled timer used for the silence after transmitted frames and the
+    ///   response timeout of a master.
+    /// * baudrate - The baudrate of `serial`, used to compute the 3.5 character silence.
+    /// * direction - Switches the transceiver, e.g. a `DriverEnable` pin.
+    ///
+    /// # Example
+    /// ```
+    /// let de = pins.p0_21.into_output_in_state(Level::Low);
+    /// let rtu = Rtu::with_direction(uart, timer, 19_200, DriverEnable(de));
+    /// ```
+    pub fn with_direction(mut serial: S, timer: T, baudrate: u32, mut direction: D) -> Self {
+        serial.enable_rx_events();
+        // Start out receiving, a failure is reported by the first send
+        let _ = direction.set_transmit(false);
+        Rtu {
+            serial,
+            timer,
+            direction,
+            silence: frame_silence(baudrate),
+            buffer: [0; MAX_ADU_LEN],
+            length: 0,
+            frame_length: 0,
+            receiving: false,
+            corrupted: false,
+        }
+    }
+
+    /// Releases the serial line, timer and direction control.
+    pub fn free(self) -> (S, T, D) {
+        (self.serial, self.timer, self.direction)
+    }
+
+    /// Transmits a frame with `address` and `pdu`.
+    ///
+    /// Blocks until the frame has been sent and the line has been silent for 3.5 characters,
+    /// so the next frame can be sent right away.
+    pub fn send(&mut self, address: u8, pdu: &[u8]) -> Result<(), Error<E>> {
+        if pdu.len() > MAX_PDU_LEN {
+            return Err(Error::InvalidRequest);
+        }
+        self.direction
+            .set_transmit(true)
+            .map_err(|_| Error::Direction)?;
+        let sent = self.write_frame(address, pdu);
+        let switched = self.direction.set_transmit(false);
+        sent?;
+        switched.map_err(|_| Error::Direction)?;
+
+        self.timer
+            .try_start(self.silence)
+            .map_err(|_| Error::Timer)?;
+        nb::block!(self.timer.try_wait()).map_err(|_| Error::Timer)
+    }
+
+    /// Polls the serial line for a complete frame.
+    ///
+    /// Must be called at least once every `RX_TRIGGER_LEVEL` character times while a frame is
+    /// being received.
+    ///
+    /// # Return
+    ///
+    /// * The length of the received frame without its CRC. The frame itself is available
+    ///   through `frame`.
+    pub fn receive(&mut self) -> nb::Result<usize, Error<E>> {
+        let event = self.serial.rx_event();
+        let count = match event {
+            RxEvent::None => return Err(WouldBlock),
+            RxEvent::Error => 1,
+            // The character timeout is only raised while the FIFO is not empty
+            RxEvent::Data => RX_TRIGGER_LEVEL - 1,
+            RxEvent::Timeout => usize::MAX,
+        };
+        if !self.receiving {
+            self.frame_length = 0;
+            self.receiving = true;
+        }
+        for _ in 0..count {
+            match self.serial.try_read() {
+                Ok(byte) => {
+                    if self.length < self.buffer.len() {
+                        self.buffer[self.length] = byte;
+                        self.length += 1;
+                    } else {
+                        self.corrupted = true;
+                    }
+                }
+                Err(WouldBlock) => break,
+                Err(_) => self.corrupted = true,
+            }
+        }
+        if event != RxEvent::Timeout {
+            return Err(WouldBlock);
+        }
+
+        let length = self.length;
+        let corrupted = self.corrupted;
+        self.length = 0;
+        self.receiving = false;
+        self.corrupted = false;
+        if corrupted && length == self.buffer.len() {
+            Err(nb::Error::Other(Error::BufferFull))
+        } else if corrupted || length < 4 || crc16(&self.buffer[..length]) != 0 {
+            // The CRC over a frame including its own CRC is zero
+            Err(nb::Error::Other(Error::Crc))
+        } else {
+            self.frame_length = length - 2;
+            Ok(self.frame_length)
+        }
+    }
+
+    /// Returns the last frame received by `receive`, starting with the unit address.
+    ///
+    /// The frame is available until the first byte of the next frame is received.
+    pub fn frame(&self) -> &[u8] {
+        &self.buffer[..self.frame_length]
+    }
+
+    fn write_frame(&mut self, address: u8, pdu: &[u8]) -> Result<(), Error<E>> {
+        let crc = pdu
+            .iter()
+            .fold(crc16(&[address]), |crc, byte| crc16_update(crc, *byte));
+        self.write_byte(address)?;
+        for byte in pdu {
+            self.write_byte(*byte)?;
+        }
+        for byte in crc.to_le_bytes().iter() {
+            self.write_byte(*byte)?;
+        }
+        nb::block!(self.serial.try_flush()).map_err(Error::Serial)
+    }
+
+    fn write_byte(&mut self, byte: u8) -> Result<(), Error<E>> {
+        nb::block!(self.serial.try_write(byte)).map_err(Error::Serial)
+    }
+}
+
+/// A Modbus RTU master, sending requests to slaves and waiting for their responses.
+pub struct Master<S, T, D = ()> {
+    rtu: Rtu<S, T, D>,
+    timeout: Microseconds<u32>,
+}
+
+impl<S, T, D, E> Master<S, T, D>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E> + RxTimeout,
+    T: CountDown<Time = Microseconds<u32>>,
+    D: Direction,
+{
+    /// Creates a master on top of an RTU transport.
+    ///
+    /// # Arguments
+    ///
+    /// * rtu - The transport requests are sent on.
+    /// * timeout - The time a slave has to start its response.
+    pub fn new(rtu: Rtu<S, T, D>, timeout: Microseconds<u32>) -> Self {
+        Master { rtu, timeout }
+    }
+
+    /// Releases the RTU transport.
+    pub fn free(self) -> Rtu<S, T, D> {
+        self.rtu
+    }
+
+    /// Sends `request` to `unit` and waits for the response.
+    ///
+    /// Broadcast requests return immediately without a response.
+    ///
+    /// # Return
+    ///
+    /// * The response PDU, starting with the function code.
+    pub fn request(&mut self, unit: u8, request: &Request) -> Result<&[u8], Error<E>> {
+        let mut pdu = [0u8; MAX_PDU_LEN];
+        let length = request.encode(&mut pdu).ok_or(Error::InvalidRequest)?;
+        self.rtu.send(unit, &pdu[..length])?;
+        if unit == BROADCAST {
+            return Ok(&[]);
+        }
+
+        self.rtu
+            .timer
+            .try_start(self.timeout)
+            .map_err(|_| Error::Timer)?;
+        loop {
+            match self.rtu.receive() {
+                Ok(_) => break,
+                Err(nb::Error::Other(e)) => return Err(e),
+                Err(WouldBlock) if !self.rtu.receiving => match self.rtu.timer.try_wait() {
+                    Err(WouldBlock) => (),
+                    Ok(()) => return Err(Error::Timeout),
+                    Err(nb::Error::Other(_)) => return Err(Error::Timer),
+                },
+                Err(WouldBlock) => (),
+            }
+        }
+
+        let frame = self.rtu.frame();
+        if frame[0] != unit {
+            return Err(Error::UnexpectedResponse);
+        }
+        let response = &frame[1..];
+        if response[0] == request.function() | pdu::EXCEPTION_FLAG {
+            let code = response.get(1).copied().unwrap_or(0);
+            Err(Exception::from_code(code)
+                .map(Error::Exception)
+                .unwrap_or(Error::UnexpectedResponse))
+        } else if response[0] != request.function() {
+            Err(Error::UnexpectedResponse)
+        } else {
+            Ok(response)
+        }
+    }
+
+    /// Reads `values.len()` coils of `unit`, starting at `address`.
+    pub fn read_coils(&mut self, unit: u8, address: u16, values: &mut [bool]) -> Result<(), Error<E>> {
+        let request = Request::ReadCoils {
+            address,
+            quantity: values.len() as u16,
+        };
+        self.read_bits(unit, &request, values)
+    }
+
+    /// Reads `values.len()` discrete inputs of `unit`, starting at `address`.
+    pub fn read_discrete_inputs(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &mut [bool],
+    ) -> Result<(), Error<E>> {
+        let request = Request::ReadDiscreteInputs {
+            address,
+            quantity: values.len() as u16,
+        };
+        self.read_bits(unit, &request, values)
+    }
+
+    /// Reads `values.len()` holding registers of `unit`, starting at `address`.
+    pub fn read_holding_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &mut [u16],
+    ) -> Result<(), Error<E>> {
+        let request = Request::ReadHoldingRegisters {
+            address,
+            quantity: values.len() as u16,
+        };
+        self.read_registers(unit, &request, values)
+    }
+
+    /// Reads `values.len()` input registers of `unit`, starting at `address`.
+    pub fn read_input_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &mut [u16],
+    ) -> Result<(), Error<E>> {
+        let request = Request::ReadInputRegisters {
+            address,
+            quantity: values.len() as u16,
+        };
+        self.read_registers(unit, &request, values)
+    }
+
+    /// Writes a single coil of `unit`.
+    pub fn write_single_coil(&mut self, unit: u8, address: u16, value: bool) -> Result<(), Error<E>> {
+        self.request(unit, &Request::WriteSingleCoil { address, value })
+            .map(|_| ())
+    }
+
+    /// Writes a single holding register of `unit`.
+    pub fn write_single_register(&mut self, unit: u8, address: u16, value: u16) -> Result<(), Error<E>> {
+        self.request(unit, &Request::WriteSingleRegister { address, value })
+            .map(|_| ())
+    }
+
+    /// Writes `values` to consecutive coils of `unit`, starting at `address`.
+    pub fn write_multiple_coils(&mut self, unit: u8, address: u16, values: &[bool]) -> Result<(), Error<E>> {
+        self.request(unit, &Request::WriteMultipleCoils { address, values })
+            .map(|_| ())
+    }
+
+    /// Writes `values` to consecutive holding registers of `unit`, starting at `address`.
+    pub fn write_multiple_registers(
+        &mut self,
+        unit: u8,
+        address: u16,
+        values: &[u16],
+    ) -> Result<(), Error<E>> {
+        self.request(unit, &Request::WriteMultipleRegisters { address, values })
+            .map(|_| ())
+    }
+
+    fn read_bits(&mut self, unit: u8, request: &Request, values: &mut [bool]) -> Result<(), Error<E>> {
+        let data = Self::response_data(self.request(unit, request)?, (values.len() + 7) / 8)?;
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = data[i / 8] & (1 << (i % 8)) != 0;
+        }
+        Ok(())
+    }
+
+    fn read_registers(&mut self, unit: u8, request: &Request, values: &mut [u16]) -> Result<(), Error<E>> {
+        let data = Self::response_data(self.request(unit, request)?, values.len() * 2)?;
+        for (value, chunk) in values.iter_mut().zip(data.chunks(2)) {
+            *value = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+        Ok(())
+    }
+
+    fn response_data(response: &[u8], byte_count: usize) -> Result<&[u8], Error<E>> {
+        match response.get(2..) {
+            Some(data) if response[1] as usize == byte_count && data.len() == byte_count => Ok(data),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+}
+
+/// A Modbus RTU slave, answering requests addressed to its unit address.
+pub struct Slave<S, T, D = ()> {
+    rtu: Rtu<S, T, D>,
+    unit: u8,
+}
+
+impl<S, T, D, E> Slave<S, T, D>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E> + RxTimeout,
+    T: CountDown<Time = Microseconds<u32>>,
+    D: Direction,
+{
+    /// Creates a slave on top of an RTU transport.
+    ///
+    /// # Arguments
+    ///
+    /// * rtu - The transport requests are received on.
+    /// * unit - The unit address of the slave, 1 to 247.
+    pub fn new(rtu: Rtu<S, T, D>, unit: u8) -> Self {
+        Slave { rtu, unit }
+    }
+
+    /// Releases the RTU transport.
+    pub fn free(self) -> Rtu<S, T, D> {
+        self.rtu
+    }
+
+    /// Polls for a request and answers it with the data model of `handler`.
+    ///
+    /// Returns `WouldBlock` until a request for this unit (or a broadcast) has been processed.
+    /// Like `Rtu::receive` it has to be polled at least once every `RX_TRIGGER_LEVEL`
+    /// character times.
+    pub fn poll<H: Handler>(&mut self, handler: &mut H) -> nb::Result<(), Error<E>> {
+        self.rtu.receive()?;
+        let frame = self.rtu.frame();
+        let unit = frame[0];
+        if unit != self.unit && unit != BROADCAST {
+            return Err(WouldBlock);
+        }
+        let mut response = [0u8; MAX_PDU_LEN];
+        let length = pdu::process(handler, &frame[1..], &mut response);
+        if unit != BROADCAST {
+            self.rtu
+                .send(self.unit, &response[..length])
+                .map_err(nb::Error::Other)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pdu::*;
+    use super::*;
+
+    /// Ten holding registers at the addresses 0 to 9 and 16 coils at the addresses 0 to 15.
+    struct Registers {
+        registers: [u16; 10],
+        coils: [bool; 16],
+    }
+
+    impl Registers {
+        fn new() -> Self {
+            let mut registers = [0; 10];
+            for (i, register) in registers.iter_mut().enumerate() {
+                *register = 0x0100 + i as u16;
+            }
+            Registers {
+                registers,
+                coils: [false; 16],
+            }
+        }
+    }
+
+    impl Handler for Registers {
+        fn read_coil(&mut self, address: u16) -> Result<bool, Exception> {
+            self.coils
+                .get(address as usize)
+                .copied()
+                .ok_or(Exception::IllegalDataAddress)
+        }
+
+        fn read_holding_register(&mut self, address: u16) -> Result<u16, Exception> {
+            self.registers
+                .get(address as usize)
+                .copied()
+                .ok_or(Exception::IllegalDataAddress)
+        }
+
+        fn write_coil(&mut self, address: u16, value: bool) -> Result<(), Exception> {
+            *self
+                .coils
+                .get_mut(address as usize)
+                .ok_or(Exception::IllegalDataAddress)? = value;
+            Ok(())
+        }
+
+        fn write_holding_register(&mut self, address: u16, value: u16) -> Result<(), Exception> {
+            *self
+                .registers
+                .get_mut(address as usize)
+                .ok_or(Exception::IllegalDataAddress)? = value;
+            Ok(())
+        }
+    }
+
+    fn process_request(handler: &mut Registers, request: &[u8], expected: &[u8]) {
+        let mut response = [0u8; MAX_PDU_LEN];
+        let length = process(handler, request, &mut response);
+        assert_eq!(&response[..length], expected);
+    }
+
+    #[test]
+    fn crc16_of_read_holding_registers() {
+        let crc = crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A]);
+        assert_eq!(crc.to_le_bytes(), [0xC5, 0xCD]);
+        assert_eq!(crc16(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]), 0);
+    }
+
+    #[test]
+    fn frame_silence_by_baudrate() {
+        assert_eq!(frame_silence(9_600), Microseconds(4_010));
+        assert_eq!(frame_silence(19_200), Microseconds(2_005));
+        assert_eq!(frame_silence(115_200), Microseconds(1_750));
+        assert_eq!(frame_silence(0), Microseconds(1_750));
+    }
+
+    #[test]
+    fn encode_requests() {
+        let mut pdu = [0u8; MAX_PDU_LEN];
+        let request = Request::ReadHoldingRegisters {
+            address: 0x0000,
+            quantity: 10,
+        };
+        let length = request.encode(&mut pdu).unwrap();
+        assert_eq!(&pdu[..length], &[0x03, 0x00, 0x00, 0x00, 0x0A]);
+
+        let request = Request::WriteSingleCoil {
+            address: 0x00AC,
+            value: true,
+        };
+        let length = request.encode(&mut pdu).unwrap();
+        assert_eq!(&pdu[..length], &[0x05, 0x00, 0xAC, 0xFF, 0x00]);
+
+        let values = [true, false, true, true, false, false, true, true, true, false];
+        let request = Request::WriteMultipleCoils {
+            address: 0x0013,
+            values: &values,
+        };
+        let length = request.encode(&mut pdu).unwrap();
+        assert_eq!(
+            &pdu[..length],
+            &[0x0F, 0x00, 0x13, 0x00, 0x0A, 0x02, 0xCD, 0x01]
+        );
+
+        let request = Request::WriteMultipleRegisters {
+            address: 0x0001,
+            values: &[0x000A, 0x0102],
+        };
+        let length = request.encode(&mut pdu).unwrap();
+        assert_eq!(
+            &pdu[..length],
+            &[0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02]
+        );
+    }
+
+    #[test]
+    fn encode_rejects_invalid_requests() {
+        let mut pdu = [0u8; MAX_PDU_LEN];
+        let empty = Request::ReadCoils {
+            address: 0,
+            quantity: 0,
+        };
+        assert_eq!(empty.encode(&mut pdu), None);
+        let too_many = Request::ReadHoldingRegisters {
+            address: 0,
+            quantity: 126,
+        };
+        assert_eq!(too_many.encode(&mut pdu), None);
+        let request = Request::ReadHoldingRegisters {
+            address: 0,
+            quantity: 1,
+        };
+        assert_eq!(request.encode(&mut pdu[..4]), None);
+    }
+
+    #[test]
+    fn process_read_holding_registers() {
+        let mut handler = Registers::new();
+        process_request(
+            &mut handler,
+            &[0x03, 0x00, 0x01, 0x00, 0x02],
+            &[0x03, 0x04, 0x01, 0x01, 0x01, 0x02],
+        );
+    }
+
+    #[test]
+    fn process_write_requests() {
+        let mut handler = Registers::new();
+        process_request(
+            &mut handler,
+            &[0x06, 0x00, 0x09, 0x12, 0x34],
+            &[0x06, 0x00, 0x09, 0x12, 0x34],
+        );
+        assert_eq!(handler.registers[9], 0x1234);
+
+        process_request(
+            &mut handler,
+            &[0x0F, 0x00, 0x02, 0x00, 0x03, 0x01, 0x05],
+            &[0x0F, 0x00, 0x02, 0x00, 0x03],
+        );
+        assert_eq!(&handler.coils[..6], &[false, false, true, false, true, false]);
+        process_request(&mut handler, &[0x01, 0x00, 0x00, 0x00, 0x06], &[0x01, 0x01, 0x14]);
+    }
+
+    #[test]
+    fn process_exceptions() {
+        let mut handler = Registers::new();
+        // Input registers are not implemented by the handler
+        process_request(&mut handler, &[0x04, 0x00, 0x00, 0x00, 0x01], &[0x84, 0x01]);
+        process_request(&mut handler, &[0x2B], &[0xAB, 0x01]);
+        process_request(&mut handler, &[], &[0x80, 0x01]);
+        process_request(&mut handler, &[0x03, 0x00, 0x09, 0x00, 0x02], &[0x83, 0x02]);
+        process_request(&mut handler, &[0x03, 0x00, 0x00, 0x00, 0x00], &[0x83, 0x03]);
+        process_request(&mut handler, &[0x05, 0x00, 0x00, 0x12, 0x34], &[0x85, 0x03]);
+        process_request(&mut handler, &[0x10, 0x00, 0x00, 0x00, 0x01, 0x02, 0x00], &[0x90, 0x03]);
+        process_request(&mut handler, &[0x03, 0xFF, 0xFF, 0x00, 0x02], &[0x83, 0x02]);
+    }
+
+    #[test]
+    fn process_with_short_response_buffer() {
+        let mut handler = Registers::new();
+        let request = [0x03, 0x00, 0x00, 0x00, 0x01];
+        let mut response = [0u8; 4];
+        assert_eq!(process(&mut handler, &request, &mut response), 2);
+        assert_eq!(&response[..2], &[0x83, 0x04]);
+        let mut response = [0u8; 1];
+        assert_eq!(process(&mut handler, &request, &mut response), 0);
+        assert_eq!(process(&mut handler, &request, &mut []), 0);
+    }
+}